@@ -0,0 +1,11 @@
+CREATE TABLE user_sessions (
+    session_id uuid NOT NULL,
+    user_id uuid NOT NULL REFERENCES users (user_id),
+    created_at timestamptz NOT NULL,
+    last_active_at timestamptz NOT NULL,
+    ip_address TEXT NULL,
+    user_agent TEXT NULL,
+    PRIMARY KEY (session_id)
+);
+
+CREATE INDEX user_sessions_user_id_idx ON user_sessions (user_id);
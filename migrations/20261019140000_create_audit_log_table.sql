@@ -0,0 +1,11 @@
+CREATE TABLE audit_log (
+    audit_log_id uuid NOT NULL,
+    actor_id uuid NOT NULL REFERENCES users (user_id),
+    action TEXT NOT NULL,
+    target TEXT NULL,
+    ip_address TEXT NULL,
+    occurred_at timestamptz NOT NULL,
+    PRIMARY KEY (audit_log_id)
+);
+
+CREATE INDEX audit_log_occurred_at_idx ON audit_log (occurred_at DESC);
@@ -0,0 +1,5 @@
+-- Issues published before authors were recorded keep a NULL author.
+ALTER TABLE newsletter_issues
+    ADD COLUMN author_id uuid NULL REFERENCES users (user_id),
+    ADD COLUMN last_edited_by uuid NULL REFERENCES users (user_id),
+    ADD COLUMN last_edited_at timestamptz NULL;
@@ -0,0 +1,9 @@
+-- Every subscriber gets a token for the unsubscribe link included in newsletter issues.
+BEGIN;
+    ALTER TABLE subscriptions ADD COLUMN unsubscribe_token TEXT NULL;
+    UPDATE subscriptions
+        SET unsubscribe_token = replace(gen_random_uuid()::text, '-', '');
+    ALTER TABLE subscriptions ALTER COLUMN unsubscribe_token SET NOT NULL;
+    ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_unsubscribe_token_key
+        UNIQUE (unsubscribe_token);
+COMMIT;
@@ -0,0 +1,8 @@
+CREATE TABLE subscriber_tags (
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (subscriber_id, tag)
+);
+
+-- The tags an issue was sent to. Issues without segments go to every confirmed subscriber.
+ALTER TABLE newsletter_issues ADD COLUMN segments TEXT[] NOT NULL DEFAULT '{}';
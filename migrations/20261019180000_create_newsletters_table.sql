@@ -0,0 +1,27 @@
+-- Subscriptions and issues belong to a newsletter. The delivery queue is scoped through the
+-- issue it delivers. Existing data moves to a default newsletter, which also serves
+-- `POST /subscriptions`.
+BEGIN;
+    CREATE TABLE newsletters (
+        newsletter_id uuid NOT NULL,
+        slug TEXT NOT NULL UNIQUE,
+        name TEXT NOT NULL,
+        -- NULL uses the sender configured for the email client.
+        sender_email TEXT NULL,
+        created_at timestamptz NOT NULL,
+        PRIMARY KEY (newsletter_id)
+    );
+    INSERT INTO newsletters (newsletter_id, slug, name, sender_email, created_at)
+    VALUES ('5b0e3c1e-2f5d-4a4b-9f3e-8d3c2a1b0c9d', 'default', 'Our newsletter', NULL, now());
+
+    ALTER TABLE subscriptions ADD COLUMN newsletter_id uuid NULL REFERENCES newsletters (newsletter_id);
+    UPDATE subscriptions SET newsletter_id = '5b0e3c1e-2f5d-4a4b-9f3e-8d3c2a1b0c9d';
+    ALTER TABLE subscriptions ALTER COLUMN newsletter_id SET NOT NULL;
+    ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+    ALTER TABLE subscriptions ADD CONSTRAINT subscriptions_newsletter_id_email_key
+        UNIQUE (newsletter_id, email);
+
+    ALTER TABLE newsletter_issues ADD COLUMN newsletter_id uuid NULL REFERENCES newsletters (newsletter_id);
+    UPDATE newsletter_issues SET newsletter_id = '5b0e3c1e-2f5d-4a4b-9f3e-8d3c2a1b0c9d';
+    ALTER TABLE newsletter_issues ALTER COLUMN newsletter_id SET NOT NULL;
+COMMIT;
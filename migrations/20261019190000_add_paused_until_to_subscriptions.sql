@@ -0,0 +1,3 @@
+-- Subscribers can pause delivery from their preference page; issues published before this
+-- time are not sent to them.
+ALTER TABLE subscriptions ADD COLUMN paused_until timestamptz NULL;
@@ -0,0 +1,3 @@
+-- Deliveries that fail are retried with a growing delay instead of being dropped.
+ALTER TABLE issue_delivery_queue ADD COLUMN n_retries SMALLINT NOT NULL DEFAULT 0;
+ALTER TABLE issue_delivery_queue ADD COLUMN execute_after timestamptz NOT NULL DEFAULT now();
@@ -0,0 +1,12 @@
+-- A token bucket shared by every delivery worker, so that together they respect the send rate.
+-- The table only ever holds one row.
+CREATE TABLE send_rate_limit(
+    id BOOLEAN PRIMARY KEY DEFAULT TRUE CHECK (id),
+    tokens DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- NULL until the first send, which then starts with a full bucket.
+    refilled_at timestamptz NULL,
+    -- Set when the email provider asks us to slow down.
+    backoff_seconds INTEGER NOT NULL DEFAULT 0,
+    paused_until timestamptz NULL
+);
+INSERT INTO send_rate_limit DEFAULT VALUES;
@@ -0,0 +1,14 @@
+-- Bounces and spam complaints reported by the email provider.
+CREATE TABLE email_events(
+    event_id uuid PRIMARY KEY,
+    -- The provider's ID for the event. Webhooks can be delivered more than once.
+    provider_event_id BIGINT NOT NULL UNIQUE,
+    -- 'bounce' or 'spam_complaint'.
+    kind TEXT NOT NULL,
+    -- The provider's bounce type, e.g. 'HardBounce' or 'SoftBounce'.
+    bounce_type TEXT NULL,
+    email TEXT NOT NULL,
+    description TEXT NULL,
+    received_at timestamptz NOT NULL
+);
+CREATE INDEX email_events_email_idx ON email_events (email);
@@ -0,0 +1,23 @@
+-- Emails and domains that must never receive anything from us.
+CREATE TABLE suppressions(
+    suppression_id uuid PRIMARY KEY,
+    -- A lowercase email, or a domain that covers every address at it.
+    address TEXT NOT NULL UNIQUE,
+    reason TEXT NOT NULL,
+    -- 'admin', 'provider', 'bounce' or 'spam_complaint'.
+    source TEXT NOT NULL,
+    -- The admin who added the entry, if any.
+    created_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    created_at timestamptz NOT NULL
+);
+
+-- Addresses that were already suppressed by the delivery worker or the bounce webhook.
+INSERT INTO suppressions (suppression_id, address, reason, source, created_at)
+SELECT DISTINCT ON (lower(email))
+    gen_random_uuid(),
+    lower(email),
+    'The email provider would not deliver to this address.',
+    'provider',
+    now()
+FROM subscriptions
+WHERE status = 'suppressed';
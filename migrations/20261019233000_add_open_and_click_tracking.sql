@@ -0,0 +1,21 @@
+-- Tracking is chosen when an issue is published.
+ALTER TABLE newsletter_issues ADD COLUMN tracking_enabled BOOLEAN NOT NULL DEFAULT false;
+
+-- One row per subscriber an issue with tracking was sent to. The token identifies them in the
+-- tracking URLs, which never contain their email.
+CREATE TABLE issue_recipients(
+    tracking_token TEXT PRIMARY KEY,
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL REFERENCES subscriptions (id) ON DELETE CASCADE,
+    opened_at timestamptz NULL,
+    clicked_at timestamptz NULL,
+    UNIQUE (newsletter_issue_id, subscriber_id)
+);
+
+-- The links of an issue that are rewritten to go through the click tracking redirect.
+CREATE TABLE issue_links(
+    newsletter_issue_id uuid NOT NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    position SMALLINT NOT NULL,
+    url TEXT NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, position)
+);
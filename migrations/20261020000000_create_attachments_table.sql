@@ -0,0 +1,16 @@
+-- Files sent along with newsletter issues. Files are uploaded first and belong to no issue
+-- until they are picked when publishing one.
+CREATE TABLE attachments(
+    attachment_id uuid PRIMARY KEY,
+    newsletter_issue_id uuid NULL REFERENCES newsletter_issues (newsletter_issue_id),
+    file_name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    content BYTEA NOT NULL,
+    -- Inline images are shown in the HTML body through a `cid:` URL instead of being listed
+    -- as files.
+    inline BOOLEAN NOT NULL,
+    uploaded_by uuid NULL REFERENCES users (user_id) ON DELETE SET NULL,
+    uploaded_at timestamptz NOT NULL
+);
+
+CREATE INDEX attachments_newsletter_issue_id_idx ON attachments (newsletter_issue_id);
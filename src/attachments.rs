@@ -0,0 +1,182 @@
+//! Files sent along with newsletter issues. Files are uploaded ahead of time and stay pending
+//! until they are picked when publishing an issue. Inline images are shown in the HTML body of
+//! the issue through a `cid:` URL made of their ID.
+
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{domain::AttachmentFile, email_client::Attachment};
+
+/// The largest file that can be uploaded.
+pub const MAX_ATTACHMENT_SIZE: usize = 5 * 1024 * 1024;
+/// The most the attachments of an issue can add up to. Postmark refuses emails over 10 MB, and
+/// attachments grow by a third once encoded.
+pub const MAX_ISSUE_ATTACHMENTS_SIZE: i64 = 7 * 1024 * 1024;
+/// Upload requests past this size are refused outright. Smaller ones are read, so that a file
+/// just over [`MAX_ATTACHMENT_SIZE`] can be reported to the admin.
+pub const MAX_UPLOAD_REQUEST_SIZE: usize = 2 * MAX_ATTACHMENT_SIZE;
+
+/// An attachment as listed in the admin area, without its content.
+pub struct AttachmentSummary {
+    pub attachment_id: Uuid,
+    pub file_name: String,
+    pub content_type: String,
+    pub size: i64,
+    pub inline: bool,
+    /// The username of the admin who uploaded the file.
+    pub uploaded_by: Option<String>,
+    pub uploaded_at: DateTime<Utc>,
+}
+
+/// An attachment of an issue, ready to be sent.
+pub struct IssueAttachment {
+    pub file_name: String,
+    pub content_type: String,
+    pub content: Vec<u8>,
+    /// The ID of inline images, which the HTML body shows with `cid:{content_id}`.
+    pub content_id: Option<String>,
+}
+
+impl IssueAttachment {
+    pub fn as_email_attachment(&self) -> Attachment<'_> {
+        Attachment {
+            name: &self.file_name,
+            content_type: &self.content_type,
+            content: &self.content,
+            content_id: self.content_id.as_deref(),
+        }
+    }
+}
+
+/// Stores an uploaded file until it is attached to an issue.
+#[tracing::instrument(name = "Store an attachment", skip(content, executor))]
+pub async fn store_attachment(
+    file: &AttachmentFile,
+    content: &[u8],
+    inline: bool,
+    uploaded_by: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Uuid, Error> {
+    let attachment_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO attachments (
+            attachment_id, file_name, content_type, content, inline, uploaded_by, uploaded_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, now())"#,
+        attachment_id,
+        file.name(),
+        file.content_type(),
+        content,
+        inline,
+        uploaded_by
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store an attachment.")?;
+
+    Ok(attachment_id)
+}
+
+/// The uploaded files that are not attached to an issue yet, most recent first.
+#[tracing::instrument(name = "Get pending attachments", skip(pool))]
+pub async fn get_pending_attachments(pool: &PgPool) -> Result<Vec<AttachmentSummary>, Error> {
+    let attachments = sqlx::query_as!(
+        AttachmentSummary,
+        r#"SELECT a.attachment_id, a.file_name, a.content_type,
+            octet_length(a.content) AS "size!", a.inline,
+            u.username AS "uploaded_by?", a.uploaded_at
+        FROM attachments a
+        LEFT JOIN users u ON u.user_id = a.uploaded_by
+        WHERE a.newsletter_issue_id IS NULL
+        ORDER BY a.uploaded_at DESC"#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the pending attachments.")?;
+
+    Ok(attachments)
+}
+
+/// Deletes an uploaded file, returning its name, or `None` if there is no such file. Files that
+/// were sent with an issue are kept.
+#[tracing::instrument(name = "Delete a pending attachment", skip(executor))]
+pub async fn delete_pending_attachment(
+    attachment_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<String>, Error> {
+    let file_name = sqlx::query!(
+        r#"DELETE FROM attachments
+        WHERE attachment_id = $1 AND newsletter_issue_id IS NULL
+        RETURNING file_name"#,
+        attachment_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to delete an attachment.")?
+    .map(|r| r.file_name);
+
+    Ok(file_name)
+}
+
+/// The number of the given files that are still pending, and the size of their content.
+#[tracing::instrument(name = "Measure pending attachments", skip(executor))]
+pub async fn measure_pending_attachments(
+    attachment_ids: &[Uuid],
+    executor: impl PgExecutor<'_>,
+) -> Result<(usize, i64), Error> {
+    let measure = sqlx::query!(
+        r#"SELECT COUNT(*) AS "count!", COALESCE(SUM(octet_length(content)), 0) AS "size!"
+        FROM attachments
+        WHERE attachment_id = ANY($1) AND newsletter_issue_id IS NULL"#,
+        attachment_ids
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to measure the pending attachments.")?;
+
+    Ok((measure.count as usize, measure.size))
+}
+
+/// Attaches pending files to an issue.
+#[tracing::instrument(name = "Attach files to an issue", skip(transaction))]
+pub async fn attach_to_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    attachment_ids: &[Uuid],
+) -> Result<(), Error> {
+    let query = sqlx::query!(
+        r#"UPDATE attachments SET newsletter_issue_id = $1
+        WHERE attachment_id = ANY($2) AND newsletter_issue_id IS NULL"#,
+        newsletter_issue_id,
+        attachment_ids
+    );
+    transaction
+        .execute(query)
+        .await
+        .context("Failed to attach files to an issue.")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get issue attachments", skip(executor))]
+pub async fn get_issue_attachments(
+    newsletter_issue_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Vec<IssueAttachment>, Error> {
+    let attachments = sqlx::query_as!(
+        IssueAttachment,
+        r#"SELECT file_name, content_type, content,
+            CASE WHEN inline THEN attachment_id::TEXT END AS content_id
+        FROM attachments
+        WHERE newsletter_issue_id = $1
+        ORDER BY uploaded_at"#,
+        newsletter_issue_id
+    )
+    .fetch_all(executor)
+    .await
+    .context("Failed to retrieve the attachments of an issue.")?;
+
+    Ok(attachments)
+}
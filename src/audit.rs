@@ -0,0 +1,163 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuditAction {
+    LogIn,
+    LogOut,
+    ChangePassword,
+    PublishNewsletter,
+    RevokeSession,
+    CreateApiToken,
+    RevokeApiToken,
+    UpdateSubscriberTags,
+    CreateNewsletter,
+    CreateSuppression,
+    DeleteSuppression,
+    UploadAttachment,
+    DeleteAttachment,
+}
+
+impl AuditAction {
+    pub const ALL: &'static [AuditAction] = &[
+        AuditAction::LogIn,
+        AuditAction::LogOut,
+        AuditAction::ChangePassword,
+        AuditAction::PublishNewsletter,
+        AuditAction::RevokeSession,
+        AuditAction::CreateApiToken,
+        AuditAction::RevokeApiToken,
+        AuditAction::UpdateSubscriberTags,
+        AuditAction::CreateNewsletter,
+        AuditAction::CreateSuppression,
+        AuditAction::DeleteSuppression,
+        AuditAction::UploadAttachment,
+        AuditAction::DeleteAttachment,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditAction::LogIn => "log_in",
+            AuditAction::LogOut => "log_out",
+            AuditAction::ChangePassword => "change_password",
+            AuditAction::PublishNewsletter => "publish_newsletter",
+            AuditAction::RevokeSession => "revoke_session",
+            AuditAction::CreateApiToken => "create_api_token",
+            AuditAction::RevokeApiToken => "revoke_api_token",
+            AuditAction::UpdateSubscriberTags => "update_subscriber_tags",
+            AuditAction::CreateNewsletter => "create_newsletter",
+            AuditAction::CreateSuppression => "create_suppression",
+            AuditAction::DeleteSuppression => "delete_suppression",
+            AuditAction::UploadAttachment => "upload_attachment",
+            AuditAction::DeleteAttachment => "delete_attachment",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<AuditAction, String> {
+        Self::ALL
+            .iter()
+            .find(|action| action.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("{} is not a valid audit action.", s))
+    }
+}
+
+pub struct AuditLogEntry {
+    pub occurred_at: DateTime<Utc>,
+    pub actor: String,
+    pub action: String,
+    pub target: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub action: Option<AuditAction>,
+    pub actor: Option<String>,
+}
+
+/// Records an administrative action. Pass the transaction that performs the action so that the
+/// entry is only stored if the action goes through.
+#[tracing::instrument(name = "Record audit event", skip(executor))]
+pub async fn record_audit_event(
+    executor: impl PgExecutor<'_>,
+    actor_id: Uuid,
+    action: AuditAction,
+    target: Option<&str>,
+    ip_address: Option<&str>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"INSERT INTO audit_log (
+            audit_log_id,
+            actor_id,
+            action,
+            target,
+            ip_address,
+            occurred_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now())"#,
+        Uuid::new_v4(),
+        actor_id,
+        action.as_str(),
+        target,
+        ip_address
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record an audit event.")?;
+
+    Ok(())
+}
+
+/// The most recent audit log entries matching the filter, newest first.
+#[tracing::instrument(name = "Get audit log", skip(pool))]
+pub async fn get_audit_log(
+    filter: &AuditLogFilter,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<AuditLogEntry>, Error> {
+    let entries = sqlx::query_as!(
+        AuditLogEntry,
+        r#"SELECT
+            a.occurred_at,
+            u.username AS actor,
+            a.action,
+            a.target,
+            a.ip_address
+        FROM audit_log a
+        JOIN users u ON u.user_id = a.actor_id
+        WHERE ($1::TEXT IS NULL OR a.action = $1)
+            AND ($2::TEXT IS NULL OR u.username = $2)
+        ORDER BY a.occurred_at DESC
+        LIMIT $3"#,
+        filter.action.map(|a| a.as_str()),
+        filter.actor.as_deref(),
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the audit log.")?;
+
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok_eq};
+
+    use super::AuditAction;
+
+    #[test]
+    fn known_actions_are_parsed_successfully() {
+        for action in AuditAction::ALL {
+            assert_ok_eq!(AuditAction::parse(action.as_str()), *action);
+        }
+    }
+
+    #[test]
+    fn unknown_actions_are_rejected() {
+        assert_err!(AuditAction::parse("delete_everything"));
+    }
+}
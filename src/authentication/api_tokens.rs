@@ -0,0 +1,221 @@
+use std::iter;
+
+use actix_web::http::Method;
+use anyhow::{Context, Error};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use secrecy::{ExposeSecret, Secret};
+use sha2::{Digest, Sha256};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+const TOKEN_PREFIX: &str = "z2p_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ApiTokenScope {
+    NewslettersPublish,
+    WebhooksPostmark,
+}
+
+impl ApiTokenScope {
+    pub const ALL: &'static [ApiTokenScope] = &[
+        ApiTokenScope::NewslettersPublish,
+        ApiTokenScope::WebhooksPostmark,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ApiTokenScope::NewslettersPublish => "newsletters:publish",
+            ApiTokenScope::WebhooksPostmark => "webhooks:postmark",
+        }
+    }
+
+    pub fn parse(s: &str) -> Result<ApiTokenScope, String> {
+        Self::ALL
+            .iter()
+            .find(|scope| scope.as_str() == s)
+            .copied()
+            .ok_or_else(|| format!("{} is not a valid API token scope.", s))
+    }
+
+    /// The scope an API token needs to call an admin endpoint. Endpoints without a
+    /// scope can only be used with a session.
+    pub fn required_for(method: &Method, path: &str) -> Option<ApiTokenScope> {
+        match (method, path) {
+            (&Method::POST, "/admin/newsletters") => Some(ApiTokenScope::NewslettersPublish),
+            (&Method::POST, "/admin/webhooks/postmark") => Some(ApiTokenScope::WebhooksPostmark),
+            _ => None,
+        }
+    }
+}
+
+pub struct ApiToken {
+    pub token_id: Uuid,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+pub struct AuthenticatedApiToken {
+    pub user_id: Uuid,
+    pub scopes: Vec<ApiTokenScope>,
+}
+
+/// Creates a new token for the user, returning its ID and plain-text value. Only a hash of the
+/// token is stored, so this is the only time it can be shown.
+#[tracing::instrument(name = "Create an API token", skip(executor))]
+pub async fn create_api_token(
+    user_id: Uuid,
+    name: &str,
+    scopes: &[ApiTokenScope],
+    executor: impl PgExecutor<'_>,
+) -> Result<(Uuid, Secret<String>), Error> {
+    let token_id = Uuid::new_v4();
+    let token = generate_api_token();
+    let scopes: Vec<String> = scopes.iter().map(|s| s.as_str().to_owned()).collect();
+
+    sqlx::query!(
+        r#"INSERT INTO api_tokens (token_id, user_id, name, token_hash, scopes, created_at)
+        VALUES ($1, $2, $3, $4, $5, now())"#,
+        token_id,
+        user_id,
+        name,
+        hash_api_token(&token),
+        &scopes
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store a new API token in the database.")?;
+
+    Ok((token_id, token))
+}
+
+#[tracing::instrument(name = "Get API tokens", skip(pool))]
+pub async fn get_api_tokens(user_id: Uuid, pool: &PgPool) -> Result<Vec<ApiToken>, Error> {
+    let tokens = sqlx::query_as!(
+        ApiToken,
+        r#"SELECT token_id, name, scopes, created_at, last_used_at
+        FROM api_tokens
+        WHERE user_id = $1
+        ORDER BY created_at DESC"#,
+        user_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the user's API tokens.")?;
+
+    Ok(tokens)
+}
+
+/// Revokes one of the user's tokens, returning `false` if there was no such token.
+#[tracing::instrument(name = "Revoke an API token", skip(executor))]
+pub async fn revoke_api_token(
+    token_id: Uuid,
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM api_tokens WHERE token_id = $1 AND user_id = $2"#,
+        token_id,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke an API token.")?
+    .rows_affected();
+
+    Ok(n_deleted_rows > 0)
+}
+
+/// Resolves a bearer token to its owner and granted scopes, recording its use.
+#[tracing::instrument(name = "Authenticate an API token", skip(token, pool))]
+pub async fn authenticate_api_token(
+    token: &Secret<String>,
+    pool: &PgPool,
+) -> Result<Option<AuthenticatedApiToken>, Error> {
+    let row = sqlx::query!(
+        r#"UPDATE api_tokens
+        SET last_used_at = now()
+        WHERE token_hash = $1
+        RETURNING user_id, scopes"#,
+        hash_api_token(token)
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to perform a query to authenticate an API token.")?;
+
+    Ok(row.map(|row| AuthenticatedApiToken {
+        user_id: row.user_id,
+        scopes: row
+            .scopes
+            .iter()
+            .filter_map(|s| ApiTokenScope::parse(s).ok())
+            .collect(),
+    }))
+}
+
+fn generate_api_token() -> Secret<String> {
+    let mut rng = rand::thread_rng();
+    let token: String = iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(40)
+        .collect();
+
+    Secret::new(format!("{}{}", TOKEN_PREFIX, token))
+}
+
+// Tokens are long and random, so a fast hash is enough to keep them safe at rest.
+fn hash_api_token(token: &Secret<String>) -> String {
+    let digest = Sha256::digest(token.expose_secret().as_bytes());
+    URL_SAFE_NO_PAD.encode(digest)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::http::Method;
+    use claims::{assert_err, assert_none, assert_ok_eq, assert_some_eq};
+
+    use super::ApiTokenScope;
+
+    #[test]
+    fn known_scopes_are_parsed_successfully() {
+        for scope in ApiTokenScope::ALL {
+            assert_ok_eq!(ApiTokenScope::parse(scope.as_str()), *scope);
+        }
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert_err!(ApiTokenScope::parse("newsletters:delete"));
+    }
+
+    #[test]
+    fn publishing_a_newsletter_requires_the_publish_scope() {
+        assert_some_eq!(
+            ApiTokenScope::required_for(&Method::POST, "/admin/newsletters"),
+            ApiTokenScope::NewslettersPublish
+        );
+    }
+
+    #[test]
+    fn postmark_webhooks_require_the_webhook_scope() {
+        assert_some_eq!(
+            ApiTokenScope::required_for(&Method::POST, "/admin/webhooks/postmark"),
+            ApiTokenScope::WebhooksPostmark
+        );
+    }
+
+    #[test]
+    fn other_admin_endpoints_cannot_be_used_with_a_token() {
+        assert_none!(ApiTokenScope::required_for(
+            &Method::GET,
+            "/admin/newsletters"
+        ));
+        assert_none!(ApiTokenScope::required_for(
+            &Method::POST,
+            "/admin/password"
+        ));
+    }
+}
@@ -0,0 +1,144 @@
+use std::iter;
+
+use actix_multipart::form::{text::Text, MultipartForm};
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error,
+    http::header::{HeaderName, AUTHORIZATION, CONTENT_TYPE},
+    web::{Bytes, Data, Payload},
+    Error, FromRequest,
+};
+use actix_web_lab::middleware::Next;
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::{session_state::TypedSession, utils};
+
+const CSRF_TOKEN_HEADER: HeaderName = HeaderName::from_static("x-csrf-token");
+
+/// The largest multipart body read to find the CSRF token among its fields, set by the app to
+/// the size of its largest upload. Without it, multipart requests need the `X-CSRF-Token`
+/// header.
+#[derive(Clone, Copy)]
+pub struct MultipartCsrfLimit(pub usize);
+
+#[derive(serde::Deserialize)]
+struct CsrfFormData {
+    csrf_token: String,
+}
+
+#[derive(MultipartForm)]
+struct CsrfMultipartData {
+    csrf_token: Text<String>,
+}
+
+/// Returns the session's CSRF token, generating one if the session doesn't have it yet.
+pub fn csrf_token(session: &TypedSession) -> Result<String, Error> {
+    if let Some(csrf_token) = session.get_csrf_token().map_err(utils::e500)? {
+        return Ok(csrf_token);
+    }
+
+    let mut rng = rand::thread_rng();
+    let csrf_token: String = iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(32)
+        .collect();
+    session
+        .insert_csrf_token(&csrf_token)
+        .map_err(utils::e500)?;
+
+    Ok(csrf_token)
+}
+
+/// Rejects state-changing requests that don't carry the session's CSRF token, either in the
+/// `csrf_token` form field or in the `X-CSRF-Token` header.
+///
+/// Multipart bodies are read up to the [`MultipartCsrfLimit`] to find the form field.
+///
+/// Requests authenticated with an API token don't rely on cookies, so they are not checked.
+pub async fn reject_invalid_csrf_tokens(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if req.method().is_safe() || req.headers().contains_key(AUTHORIZATION) {
+        return next.call(req).await;
+    }
+
+    let session = {
+        let (http_request, payload) = req.parts_mut();
+        TypedSession::from_request(http_request, payload).await
+    }?;
+    let expected_token = session.get_csrf_token().map_err(utils::e500)?;
+
+    let submitted_token = match req.headers().get(CSRF_TOKEN_HEADER) {
+        Some(header) => header.to_str().ok().map(str::to_owned),
+        None if is_multipart(&req) => {
+            match req.app_data::<Data<MultipartCsrfLimit>>().map(|l| l.0) {
+                Some(limit) => multipart_csrf_token(&mut req, limit).await?,
+                None => None,
+            }
+        }
+        None => {
+            let body = req.extract::<Bytes>().await?;
+            let csrf_token = serde_urlencoded::from_bytes::<CsrfFormData>(&body)
+                .ok()
+                .map(|f| f.csrf_token);
+            restore_payload(&mut req, body);
+
+            csrf_token
+        }
+    };
+
+    match (expected_token, submitted_token) {
+        (Some(expected), Some(submitted)) if constant_time_eq(&expected, &submitted) => {
+            next.call(req).await
+        }
+        _ => Err(error::ErrorForbidden(
+            "The request is missing a valid CSRF token.",
+        )),
+    }
+}
+
+async fn multipart_csrf_token(
+    req: &mut ServiceRequest,
+    limit: usize,
+) -> Result<Option<String>, Error> {
+    let body = req
+        .extract::<Payload>()
+        .await?
+        .to_bytes_limited(limit)
+        .await
+        .map_err(|_| error::ErrorPayloadTooLarge("The request body is too large."))??;
+
+    restore_payload(req, body.clone());
+    let csrf_token = req
+        .extract::<MultipartForm<CsrfMultipartData>>()
+        .await
+        .ok()
+        .map(|f| f.into_inner().csrf_token.into_inner());
+    restore_payload(req, body);
+
+    Ok(csrf_token)
+}
+
+fn is_multipart(req: &ServiceRequest) -> bool {
+    req.headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with("multipart/form-data"))
+}
+
+/// Puts back a body that was read to find the token, for the handler to extract.
+fn restore_payload(req: &mut ServiceRequest, body: Bytes) {
+    let (_, mut payload) = actix_http::h1::Payload::create(true);
+    payload.unread_data(body);
+    req.set_payload(payload.into());
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
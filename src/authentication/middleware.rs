@@ -3,14 +3,23 @@ use std::{fmt, ops};
 use actix_web::{
     body::MessageBody,
     dev::{ServiceRequest, ServiceResponse},
-    error::InternalError,
-    Error, FromRequest, HttpMessage,
+    error::{self, InternalError},
+    http::header::{AUTHORIZATION, WWW_AUTHENTICATE},
+    web::Data,
+    Error, FromRequest, HttpMessage, HttpResponse,
 };
 use actix_web_lab::middleware::Next;
 use anyhow::anyhow;
+use secrecy::Secret;
+use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{session_state::TypedSession, utils};
+use crate::{
+    authentication::{self, ApiTokenScope},
+    configuration::SessionSettings,
+    session_state::TypedSession,
+    utils,
+};
 
 #[derive(Copy, Clone, Debug)]
 pub struct UserId(Uuid);
@@ -33,20 +42,88 @@ pub async fn reject_anonymous_users(
     mut req: ServiceRequest,
     next: Next<impl MessageBody>,
 ) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    if let Some(token) = bearer_token(&req)? {
+        let user_id = authenticate_api_token(&req, token).await?;
+        req.extensions_mut().insert(UserId(user_id));
+        return next.call(req).await;
+    }
+
     let session = {
         let (http_request, payload) = req.parts_mut();
         TypedSession::from_request(http_request, payload).await
     }?;
 
-    match session.get_user_id().map_err(utils::e500)? {
-        Some(user_id) => {
-            req.extensions_mut().insert(UserId(user_id));
-            next.call(req).await
-        }
-        None => {
-            let response = utils::see_other("/login");
-            let e = anyhow!("The user has not logged in");
-            Err(InternalError::from_response(e, response).into())
-        }
+    let user_id = session.get_user_id().map_err(utils::e500)?;
+    let session_id = session.get_session_id().map_err(utils::e500)?;
+    let (user_id, session_id) = match (user_id, session_id) {
+        (Some(user_id), Some(session_id)) => (user_id, session_id),
+        _ => return Err(login_redirect(anyhow!("The user has not logged in"))),
+    };
+
+    let pool = db_pool(&req)?;
+    let settings = req
+        .app_data::<Data<SessionSettings>>()
+        .cloned()
+        .ok_or_else(|| utils::e500("The session settings are not registered."))?;
+    let is_active = authentication::touch_session(session_id, user_id, &settings, &pool)
+        .await
+        .map_err(utils::e500)?;
+    if !is_active {
+        return Err(login_redirect(anyhow!(
+            "The session has been revoked or has expired"
+        )));
+    }
+
+    req.extensions_mut().insert(UserId(user_id));
+    next.call(req).await
+}
+
+fn login_redirect(e: anyhow::Error) -> Error {
+    let response = utils::see_other("/login");
+    InternalError::from_response(e, response).into()
+}
+
+fn bearer_token(req: &ServiceRequest) -> Result<Option<Secret<String>>, Error> {
+    let header = match req.headers().get(AUTHORIZATION) {
+        Some(header) => header,
+        None => return Ok(None),
+    };
+
+    header
+        .to_str()
+        .ok()
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(|token| Some(Secret::new(token.trim().to_owned())))
+        .ok_or_else(|| unauthorized(anyhow!("The 'Authorization' header is not a bearer token")))
+}
+
+async fn authenticate_api_token(
+    req: &ServiceRequest,
+    token: Secret<String>,
+) -> Result<Uuid, Error> {
+    let pool = db_pool(req)?;
+    let api_token = authentication::authenticate_api_token(&token, &pool)
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| unauthorized(anyhow!("Invalid API token")))?;
+
+    match ApiTokenScope::required_for(req.method(), req.path()) {
+        Some(scope) if api_token.scopes.contains(&scope) => Ok(api_token.user_id),
+        _ => Err(error::ErrorForbidden(
+            "The API token is not allowed to perform this action.",
+        )),
     }
 }
+
+fn db_pool(req: &ServiceRequest) -> Result<Data<PgPool>, Error> {
+    req.app_data::<Data<PgPool>>()
+        .cloned()
+        .ok_or_else(|| utils::e500("The database pool is not registered."))
+}
+
+fn unauthorized(e: anyhow::Error) -> Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, "Bearer"))
+        .finish();
+    InternalError::from_response(e, response).into()
+}
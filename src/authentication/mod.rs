@@ -1,5 +1,21 @@
+mod api_tokens;
+mod csrf;
 mod middleware;
 mod password;
+mod password_policy;
+mod sessions;
 
+pub use api_tokens::{
+    authenticate_api_token, create_api_token, get_api_tokens, revoke_api_token, ApiToken,
+    ApiTokenScope, AuthenticatedApiToken,
+};
+pub use csrf::{csrf_token, reject_invalid_csrf_tokens, MultipartCsrfLimit};
 pub use middleware::{reject_anonymous_users, UserId};
-pub use password::{change_password, validate_credentials, AuthError, Credentials};
+pub use password::{
+    change_password, validate_credentials, AuthError, Credentials, PasswordHashing,
+};
+pub use password_policy::{PasswordPolicy, PasswordPolicyError};
+pub use sessions::{
+    get_active_sessions, register_session, revoke_other_sessions, revoke_session, touch_session,
+    ActiveSession,
+};
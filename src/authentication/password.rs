@@ -4,7 +4,7 @@ use argon2::{
     PasswordVerifier, Version,
 };
 use secrecy::{ExposeSecret, Secret};
-use sqlx::PgPool;
+use sqlx::{PgExecutor, PgPool};
 use uuid::Uuid;
 
 use crate::telemetry;
@@ -22,18 +22,40 @@ pub enum AuthError {
     UnexpectedError(#[from] Error),
 }
 
-#[tracing::instrument("Validate credentials", skip(credentials, pool))]
+/// The Argon2 parameters of new password hashes, with a hash of a random password computed
+/// with them. Unknown usernames are checked against that hash, so that they take as long to
+/// reject as known ones and cannot be told apart by timing.
+pub struct PasswordHashing {
+    params: Params,
+    fallback_hash: Secret<String>,
+}
+
+impl PasswordHashing {
+    pub fn new(params: Params) -> Result<Self, Error> {
+        let fallback_password = Secret::new(Uuid::new_v4().to_string());
+        let fallback_hash = compute_password_hash(fallback_password, params.clone())
+            .context("Failed to hash the fallback password.")?;
+
+        Ok(Self {
+            params,
+            fallback_hash,
+        })
+    }
+
+    pub fn params(&self) -> &Params {
+        &self.params
+    }
+}
+
+#[tracing::instrument("Validate credentials", skip(credentials, hashing, pool))]
 pub async fn validate_credentials(
     credentials: Credentials,
+    hashing: &PasswordHashing,
     pool: &PgPool,
 ) -> Result<Uuid, AuthError> {
+    let params = hashing.params();
     let mut user_id = None;
-    let mut expected_password_hash = Secret::new(
-        "$argon2id$v=19$m=15000,t=2,p=1$\
-        gZiV/M1gPc22ElAH/Jh1Hw$\
-        CWOrkoo7oJBQ/iyh7uJ0LO2aLEfrHwTWllSAxT0zRno"
-            .to_string(),
-    );
+    let mut expected_password_hash = hashing.fallback_hash.clone();
 
     if let Some((stored_user_id, stored_password_hash)) =
         get_stored_credentials(&credentials.username, pool).await?
@@ -42,15 +64,25 @@ pub async fn validate_credentials(
         expected_password_hash = stored_password_hash;
     }
 
+    let password_candidate = credentials.password.clone();
+    let password_hash = expected_password_hash.clone();
     telemetry::spawn_blocking_with_tracing(move || {
-        verify_password_hash(expected_password_hash, credentials.password)
+        verify_password_hash(password_hash, password_candidate)
     })
     .await
     .context("Failed to spawn blocking task.")??;
 
-    user_id
+    let user_id = user_id
         .ok_or_else(|| anyhow!("Unknown username."))
-        .map_err(AuthError::InvalidCredentials)
+        .map_err(AuthError::InvalidCredentials)?;
+
+    if needs_rehash(&expected_password_hash, params)? {
+        if let Err(e) = change_password(user_id, credentials.password, params, pool).await {
+            tracing::warn!(error.cause_chain = ?e, error.message = %e, "Failed to upgrade the stored password hash.");
+        }
+    }
+
+    Ok(user_id)
 }
 
 #[tracing::instrument("Get stored credentials", skip(username, pool))]
@@ -90,14 +122,38 @@ fn verify_password_hash(
         .map_err(AuthError::InvalidCredentials)
 }
 
-#[tracing::instrument(name = "Change password", skip(password, pool))]
+#[tracing::instrument(
+    name = "Check if password hash needs rehash",
+    skip(password_hash, params)
+)]
+fn needs_rehash(password_hash: &Secret<String>, params: &Params) -> Result<bool, Error> {
+    let password_hash = PasswordHash::new(password_hash.expose_secret())
+        .context("Failed to parse hash in PHC string format.")?;
+
+    let algorithm = Algorithm::try_from(password_hash.algorithm)
+        .context("Failed to parse the algorithm of the stored hash.")?;
+    if algorithm != Algorithm::Argon2id || password_hash.version != Some(Version::V0x13.into()) {
+        return Ok(true);
+    }
+
+    let stored_params = Params::try_from(&password_hash)
+        .context("Failed to parse the parameters of the stored hash.")?;
+
+    Ok(stored_params.m_cost() < params.m_cost()
+        || stored_params.t_cost() < params.t_cost()
+        || stored_params.p_cost() < params.p_cost())
+}
+
+#[tracing::instrument(name = "Change password", skip(password, params, executor))]
 pub async fn change_password(
     user_id: Uuid,
     password: Secret<String>,
-    pool: &PgPool,
+    params: &Params,
+    executor: impl PgExecutor<'_>,
 ) -> Result<(), Error> {
+    let params = params.clone();
     let password_hash =
-        telemetry::spawn_blocking_with_tracing(move || compute_password_hash(password))
+        telemetry::spawn_blocking_with_tracing(move || compute_password_hash(password, params))
             .await?
             .context("Failed to hash password.")?;
 
@@ -106,22 +162,21 @@ pub async fn change_password(
         password_hash.expose_secret(),
         user_id
     )
-    .execute(pool)
+    .execute(executor)
     .await
     .context("Failed to change user's password in the database.")?;
 
     Ok(())
 }
 
-fn compute_password_hash(password: Secret<String>) -> Result<Secret<String>, Error> {
+fn compute_password_hash(
+    password: Secret<String>,
+    params: Params,
+) -> Result<Secret<String>, Error> {
     let salt = SaltString::generate(&mut rand::thread_rng());
-    let password_hash = Argon2::new(
-        Algorithm::Argon2id,
-        Version::V0x13,
-        Params::new(15000, 2, 1, None).unwrap(),
-    )
-    .hash_password(password.expose_secret().as_bytes(), &salt)?
-    .to_string();
+    let password_hash = Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+        .hash_password(password.expose_secret().as_bytes(), &salt)?
+        .to_string();
 
     Ok(Secret::new(password_hash))
 }
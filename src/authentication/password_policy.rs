@@ -0,0 +1,139 @@
+use secrecy::{ExposeSecret, Secret};
+use unicode_segmentation::UnicodeSegmentation;
+
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+#[derive(Clone)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    pub reject_common_passwords: bool,
+}
+
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum PasswordPolicyError {
+    #[error("The new password must be at least {0} characters long.")]
+    TooShort(usize),
+    #[error("The new password must be at most {0} characters long.")]
+    TooLong(usize),
+    #[error("The new password must be different from the current one.")]
+    SameAsCurrentPassword,
+    #[error("The new password must not contain your username.")]
+    ContainsUsername,
+    #[error("The new password is too common, please choose a different one.")]
+    CommonPassword,
+}
+
+impl PasswordPolicy {
+    pub fn check(
+        &self,
+        new_password: &Secret<String>,
+        current_password: &Secret<String>,
+        username: &str,
+    ) -> Result<(), PasswordPolicyError> {
+        let new_password = new_password.expose_secret();
+
+        let length = new_password.graphemes(true).count();
+        if length < self.min_length {
+            return Err(PasswordPolicyError::TooShort(self.min_length));
+        }
+        if length > self.max_length {
+            return Err(PasswordPolicyError::TooLong(self.max_length));
+        }
+
+        if new_password == current_password.expose_secret() {
+            return Err(PasswordPolicyError::SameAsCurrentPassword);
+        }
+
+        let username = username.trim().to_lowercase();
+        if !username.is_empty() && new_password.to_lowercase().contains(&username) {
+            return Err(PasswordPolicyError::ContainsUsername);
+        }
+
+        if self.reject_common_passwords && is_common_password(new_password) {
+            return Err(PasswordPolicyError::CommonPassword);
+        }
+
+        Ok(())
+    }
+}
+
+fn is_common_password(password: &str) -> bool {
+    COMMON_PASSWORDS
+        .lines()
+        .any(|common| common.eq_ignore_ascii_case(password))
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err_eq, assert_ok};
+    use secrecy::Secret;
+
+    use super::{PasswordPolicy, PasswordPolicyError};
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: 12,
+            max_length: 128,
+            reject_common_passwords: true,
+        }
+    }
+
+    fn check(new_password: &str) -> Result<(), PasswordPolicyError> {
+        policy().check(
+            &Secret::new(new_password.to_string()),
+            &Secret::new("my current password".to_string()),
+            "ursula",
+        )
+    }
+
+    #[test]
+    fn a_password_shorter_than_the_minimum_is_rejected() {
+        assert_err_eq!(check("short"), PasswordPolicyError::TooShort(12));
+    }
+
+    #[test]
+    fn a_password_longer_than_the_maximum_is_rejected() {
+        assert_err_eq!(check(&"a".repeat(129)), PasswordPolicyError::TooLong(128));
+    }
+
+    #[test]
+    fn the_current_password_is_rejected() {
+        assert_err_eq!(
+            check("my current password"),
+            PasswordPolicyError::SameAsCurrentPassword
+        );
+    }
+
+    #[test]
+    fn a_password_containing_the_username_is_rejected() {
+        assert_err_eq!(
+            check("Ursula-the-great"),
+            PasswordPolicyError::ContainsUsername
+        );
+    }
+
+    #[test]
+    fn a_common_password_is_rejected() {
+        assert_err_eq!(check("Password1234"), PasswordPolicyError::CommonPassword);
+    }
+
+    #[test]
+    fn common_passwords_are_allowed_if_the_check_is_disabled() {
+        let policy = PasswordPolicy {
+            reject_common_passwords: false,
+            ..policy()
+        };
+
+        assert_ok!(policy.check(
+            &Secret::new("password1234".to_string()),
+            &Secret::new("my current password".to_string()),
+            "ursula",
+        ));
+    }
+
+    #[test]
+    fn a_strong_password_is_accepted() {
+        assert_ok!(check("the left hand of darkness"));
+    }
+}
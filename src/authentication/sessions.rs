@@ -0,0 +1,146 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Duration, Utc};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::configuration::SessionSettings;
+
+pub struct ActiveSession {
+    pub session_id: Uuid,
+    pub created_at: DateTime<Utc>,
+    pub last_active_at: DateTime<Utc>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+}
+
+#[tracing::instrument(name = "Register a new session", skip(executor, user_agent))]
+pub async fn register_session(
+    user_id: Uuid,
+    ip_address: Option<&str>,
+    user_agent: Option<&str>,
+    executor: impl PgExecutor<'_>,
+) -> Result<Uuid, Error> {
+    let session_id = Uuid::new_v4();
+    sqlx::query!(
+        r#"INSERT INTO user_sessions (
+            session_id,
+            user_id,
+            created_at,
+            last_active_at,
+            ip_address,
+            user_agent
+        )
+        VALUES ($1, $2, now(), now(), $3, $4)"#,
+        session_id,
+        user_id,
+        ip_address,
+        user_agent
+    )
+    .execute(executor)
+    .await
+    .context("Failed to register a new session in the database.")?;
+
+    Ok(session_id)
+}
+
+/// Records activity on a session, returning `false` if it has been revoked or has expired.
+#[tracing::instrument(name = "Touch session", skip(settings, pool))]
+pub async fn touch_session(
+    session_id: Uuid,
+    user_id: Uuid,
+    settings: &SessionSettings,
+    pool: &PgPool,
+) -> Result<bool, Error> {
+    let (created_after, active_after) = expiry_cutoffs(settings)?;
+    let n_updated_rows = sqlx::query!(
+        r#"UPDATE user_sessions
+        SET last_active_at = now()
+        WHERE session_id = $1
+            AND user_id = $2
+            AND created_at > $3
+            AND last_active_at > $4"#,
+        session_id,
+        user_id,
+        created_after,
+        active_after
+    )
+    .execute(pool)
+    .await
+    .context("Failed to update the session's last activity.")?
+    .rows_affected();
+
+    Ok(n_updated_rows > 0)
+}
+
+#[tracing::instrument(name = "Get active sessions", skip(settings, pool))]
+pub async fn get_active_sessions(
+    user_id: Uuid,
+    settings: &SessionSettings,
+    pool: &PgPool,
+) -> Result<Vec<ActiveSession>, Error> {
+    let (created_after, active_after) = expiry_cutoffs(settings)?;
+    let sessions = sqlx::query_as!(
+        ActiveSession,
+        r#"SELECT session_id, created_at, last_active_at, ip_address, user_agent
+        FROM user_sessions
+        WHERE user_id = $1 AND created_at > $2 AND last_active_at > $3
+        ORDER BY last_active_at DESC"#,
+        user_id,
+        created_after,
+        active_after
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the user's active sessions.")?;
+
+    Ok(sessions)
+}
+
+/// Revokes one of the user's sessions, returning `false` if there was no such session.
+#[tracing::instrument(name = "Revoke session", skip(executor))]
+pub async fn revoke_session(
+    session_id: Uuid,
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"DELETE FROM user_sessions WHERE session_id = $1 AND user_id = $2"#,
+        session_id,
+        user_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke a session.")?
+    .rows_affected();
+
+    Ok(n_deleted_rows > 0)
+}
+
+#[tracing::instrument(name = "Revoke other sessions", skip(executor))]
+pub async fn revoke_other_sessions(
+    current_session_id: Option<Uuid>,
+    user_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        r#"DELETE FROM user_sessions
+        WHERE user_id = $1 AND session_id IS DISTINCT FROM $2"#,
+        user_id,
+        current_session_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to revoke the user's other sessions.")?;
+
+    Ok(())
+}
+
+/// Sessions created or last used before these instants have expired.
+fn expiry_cutoffs(settings: &SessionSettings) -> Result<(DateTime<Utc>, DateTime<Utc>), Error> {
+    let now = Utc::now();
+    let ttl = Duration::from_std(settings.ttl()).context("Invalid session TTL.")?;
+    let idle_timeout =
+        Duration::from_std(settings.idle_timeout()).context("Invalid session idle timeout.")?;
+
+    Ok((now - ttl, now - idle_timeout))
+}
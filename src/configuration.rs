@@ -1,14 +1,30 @@
+use actix_web::{
+    cookie::SameSite,
+    http::header::{
+        HeaderMap, HeaderValue, InvalidHeaderValue, CONTENT_SECURITY_POLICY, REFERRER_POLICY,
+        STRICT_TRANSPORT_SECURITY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+    },
+};
+use argon2::Params;
 use config::{Config, ConfigError, File};
 use secrecy::{ExposeSecret, Secret};
-use serde_aux::field_attributes::deserialize_number_from_string;
+use serde_aux::field_attributes::{
+    deserialize_number_from_string, deserialize_option_number_from_string,
+};
 use sqlx::{
     postgres::{PgConnectOptions, PgSslMode},
     ConnectOptions,
 };
-use std::{env, time::Duration};
+use std::{env, net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
 use tracing_log::log::LevelFilter;
 
-use crate::{domain::SubscriberEmail, email_client::EmailClient};
+use crate::{
+    authentication::PasswordPolicy,
+    domain::SubscriberEmail,
+    email_client::{EmailTransport, FileTransport, PostmarkTransport, SmtpTransport},
+    security_headers::SecurityHeaders,
+    send_rate::SendRateLimiter,
+};
 
 enum Environment {
     Local,
@@ -80,27 +96,76 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    /// The reverse proxies trusted to report the client's IP address in forwarded headers.
+    pub trusted_proxies: Vec<IpAddr>,
+}
+
+#[derive(Clone, Copy, Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailTransportKind {
+    Postmark,
+    Smtp,
+    File,
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub username: String,
+    pub password: Secret<String>,
 }
 
 #[derive(Clone, serde::Deserialize)]
 pub struct EmailClientSettings {
+    pub transport: EmailTransportKind,
+    /// The Postmark API, used by the `postmark` transport.
     pub base_url: String,
     pub sender_email: String,
+    /// The Postmark server token, used by the `postmark` transport.
     pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    /// Required by the `smtp` transport.
+    pub smtp: Option<SmtpSettings>,
+    /// Where the `file` transport writes emails.
+    pub file_directory: Option<PathBuf>,
 }
 
 impl EmailClientSettings {
-    pub fn client(self) -> EmailClient {
-        let sender_email = self.sender().expect("Invalid sender email address.");
+    pub fn client(self) -> Result<Arc<dyn EmailTransport>, anyhow::Error> {
+        let sender_email = self.sender().map_err(anyhow::Error::msg)?;
         let timeout = self.timeout();
 
-        EmailClient::new(
-            self.base_url,
-            sender_email,
-            self.authorization_token,
-            timeout,
-        )
+        let client: Arc<dyn EmailTransport> = match self.transport {
+            EmailTransportKind::Postmark => Arc::new(PostmarkTransport::new(
+                self.base_url,
+                sender_email,
+                self.authorization_token,
+                timeout,
+            )),
+            EmailTransportKind::Smtp => {
+                let smtp = self.smtp.ok_or_else(|| {
+                    anyhow::anyhow!("The smtp email transport needs smtp settings.")
+                })?;
+                Arc::new(SmtpTransport::new(
+                    &smtp.host,
+                    smtp.port,
+                    smtp.username,
+                    smtp.password,
+                    sender_email,
+                    timeout,
+                )?)
+            }
+            EmailTransportKind::File => {
+                let directory = self.file_directory.ok_or_else(|| {
+                    anyhow::anyhow!("The file email transport needs a file_directory.")
+                })?;
+                Arc::new(FileTransport::new(directory, sender_email)?)
+            }
+        };
+
+        Ok(client)
     }
 
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
@@ -112,11 +177,163 @@ impl EmailClientSettings {
     }
 }
 
+#[derive(Clone, serde::Deserialize)]
+pub struct DeliverySettings {
+    /// Shared by every delivery worker. When unset, only the email provider limits the rate.
+    #[serde(default, deserialize_with = "deserialize_option_number_from_string")]
+    pub messages_per_minute: Option<u32>,
+    /// The most messages that can be sent at once when the workers have been idle. Anything
+    /// less than a full batch splits batches into several requests.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub burst: u32,
+}
+
+impl DeliverySettings {
+    pub fn rate_limiter(&self) -> SendRateLimiter {
+        SendRateLimiter::new(self.messages_per_minute, self.burst)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct PasswordHashingSettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub memory_cost: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub time_cost: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub parallelism: u32,
+}
+
+impl PasswordHashingSettings {
+    pub fn params(&self) -> Result<Params, argon2::Error> {
+        Params::new(self.memory_cost, self.time_cost, self.parallelism, None)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct PasswordPolicySettings {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub min_length: usize,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_length: usize,
+    pub reject_common_passwords: bool,
+}
+
+impl PasswordPolicySettings {
+    pub fn policy(&self) -> PasswordPolicy {
+        PasswordPolicy {
+            min_length: self.min_length,
+            max_length: self.max_length,
+            reject_common_passwords: self.reject_common_passwords,
+        }
+    }
+}
+
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SameSitePolicy {
+    Strict,
+    Lax,
+    None,
+}
+
+impl From<SameSitePolicy> for SameSite {
+    fn from(value: SameSitePolicy) -> Self {
+        match value {
+            SameSitePolicy::Strict => SameSite::Strict,
+            SameSitePolicy::Lax => SameSite::Lax,
+            SameSitePolicy::None => SameSite::None,
+        }
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SessionSettings {
+    pub cookie_name: String,
+    pub cookie_secure: bool,
+    pub cookie_same_site: SameSitePolicy,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub ttl_seconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub idle_timeout_seconds: u64,
+}
+
+impl SessionSettings {
+    /// The maximum lifetime of a session, regardless of activity.
+    pub fn ttl(&self) -> Duration {
+        Duration::from_secs(self.ttl_seconds)
+    }
+
+    /// How long a session can go unused before it expires.
+    pub fn idle_timeout(&self) -> Duration {
+        Duration::from_secs(self.idle_timeout_seconds)
+    }
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SecurityHeaderValues {
+    pub content_security_policy: String,
+    pub frame_options: String,
+    pub referrer_policy: String,
+}
+
+#[derive(Clone, serde::Deserialize)]
+pub struct SecurityHeadersSettings {
+    /// HSTS is only sent when this is non-zero.
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub hsts_max_age_seconds: u64,
+    pub default: SecurityHeaderValues,
+    /// Used instead of `default` for the login page and the admin area.
+    pub restricted: SecurityHeaderValues,
+}
+
+impl SecurityHeadersSettings {
+    pub fn headers(&self) -> Result<SecurityHeaders, InvalidHeaderValue> {
+        Ok(SecurityHeaders::new(
+            self.header_map(&self.default)?,
+            self.header_map(&self.restricted)?,
+        ))
+    }
+
+    fn header_map(&self, values: &SecurityHeaderValues) -> Result<HeaderMap, InvalidHeaderValue> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            CONTENT_SECURITY_POLICY,
+            HeaderValue::from_str(&values.content_security_policy)?,
+        );
+        headers.insert(
+            X_FRAME_OPTIONS,
+            HeaderValue::from_str(&values.frame_options)?,
+        );
+        headers.insert(
+            REFERRER_POLICY,
+            HeaderValue::from_str(&values.referrer_policy)?,
+        );
+        headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+        if self.hsts_max_age_seconds > 0 {
+            headers.insert(
+                STRICT_TRANSPORT_SECURITY,
+                HeaderValue::from_str(&format!(
+                    "max-age={}; includeSubDomains",
+                    self.hsts_max_age_seconds
+                ))?,
+            );
+        }
+
+        Ok(headers)
+    }
+}
+
 #[derive(Clone, serde::Deserialize)]
 pub struct Settings {
     pub database: DatabaseSettings,
     pub application: ApplicationSettings,
     pub email_client: EmailClientSettings,
+    pub delivery: DeliverySettings,
+    pub password_hashing: PasswordHashingSettings,
+    pub password_policy: PasswordPolicySettings,
+    pub session: SessionSettings,
+    pub security_headers: SecurityHeadersSettings,
     pub redis_uri: Secret<String>,
 }
 
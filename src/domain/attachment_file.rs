@@ -0,0 +1,192 @@
+const MAX_NAME_LENGTH: usize = 255;
+
+/// The content types that can be attached to an issue, with the file extensions each one may
+/// use. Postmark rejects executables and other risky files by their extension, so anything
+/// outside this list is refused at upload time instead.
+const ALLOWED_TYPES: &[(&str, &[&str])] = &[
+    ("image/png", &["png"]),
+    ("image/jpeg", &["jpg", "jpeg"]),
+    ("image/gif", &["gif"]),
+    ("application/pdf", &["pdf"]),
+    ("text/plain", &["txt"]),
+    ("text/csv", &["csv"]),
+    ("text/calendar", &["ics"]),
+];
+
+/// The bytes that files of each binary type start with. Text files have no such signature.
+const SIGNATURES: &[(&str, &[&[u8]])] = &[
+    ("image/png", &[b"\x89PNG\r\n\x1a\n"]),
+    ("image/jpeg", &[b"\xff\xd8\xff"]),
+    ("image/gif", &[b"GIF87a", b"GIF89a"]),
+    ("application/pdf", &[b"%PDF-"]),
+];
+
+/// The name and content type of an uploaded file.
+#[derive(Debug)]
+pub struct AttachmentFile {
+    name: String,
+    content_type: &'static str,
+}
+
+impl AttachmentFile {
+    /// Browsers may send the full path of the file, of which only the last component is kept.
+    pub fn parse(name: String, content_type: String) -> Result<AttachmentFile, String> {
+        let name = name
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_owned();
+        if name.is_empty() || name.chars().count() > MAX_NAME_LENGTH {
+            return Err(format!(
+                "File names must be between 1 and {} characters long.",
+                MAX_NAME_LENGTH
+            ));
+        }
+        if name.chars().any(char::is_control) {
+            return Err(format!("{} is not a valid file name.", name));
+        }
+
+        // Parameters such as `charset` are not needed to send the file.
+        let essence = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        let Some((content_type, extensions)) = ALLOWED_TYPES
+            .iter()
+            .find(|(allowed, _)| *allowed == essence)
+        else {
+            return Err(format!("{} files cannot be attached to an issue.", essence));
+        };
+        let extension = name
+            .rsplit_once('.')
+            .map(|(_, extension)| extension.to_lowercase());
+        if !extension.is_some_and(|e| extensions.contains(&e.as_str())) {
+            return Err(format!(
+                "{} files must be named with one of these extensions: {}.",
+                content_type,
+                extensions.join(", ")
+            ));
+        }
+
+        Ok(Self { name, content_type })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn content_type(&self) -> &str {
+        self.content_type
+    }
+
+    /// Both the name and the content type of a file are chosen by the browser, so the content
+    /// of binary files must also look like their type.
+    pub fn check_content(&self, content: &[u8]) -> Result<(), String> {
+        let signatures = SIGNATURES
+            .iter()
+            .find(|(content_type, _)| *content_type == self.content_type)
+            .map(|(_, signatures)| *signatures)
+            .unwrap_or_default();
+        if !signatures.is_empty() && !signatures.iter().any(|s| content.starts_with(s)) {
+            return Err(format!(
+                "{} is not a valid {} file.",
+                self.name, self.content_type
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Only images can be shown inline in the body of an issue.
+    pub fn is_image(&self) -> bool {
+        self.content_type.starts_with("image/")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::AttachmentFile;
+
+    #[test]
+    fn allowed_files_are_accepted() {
+        let file = AttachmentFile::parse("Report.PDF".into(), "application/pdf".into()).unwrap();
+        assert_eq!("Report.PDF", file.name());
+        assert_eq!("application/pdf", file.content_type());
+        assert!(!file.is_image());
+
+        let image = AttachmentFile::parse("logo.jpg".into(), "image/jpeg".into()).unwrap();
+        assert!(image.is_image());
+    }
+
+    #[test]
+    fn content_type_parameters_are_ignored() {
+        let file = AttachmentFile::parse("notes.txt".into(), "Text/Plain; charset=utf-8".into());
+        assert_eq!("text/plain", file.unwrap().content_type());
+    }
+
+    #[test]
+    fn directories_are_stripped_from_the_name() {
+        let file = AttachmentFile::parse(r"C:\Users\ursula\logo.png".into(), "image/png".into());
+        assert_eq!("logo.png", file.unwrap().name());
+    }
+
+    #[test]
+    fn disallowed_content_types_are_rejected() {
+        for content_type in ["application/x-msdownload", "text/html", "image/svg+xml", ""] {
+            assert_err!(AttachmentFile::parse(
+                "file.png".into(),
+                content_type.into()
+            ));
+        }
+    }
+
+    #[test]
+    fn extensions_must_match_the_content_type() {
+        assert_err!(AttachmentFile::parse(
+            "setup.exe".into(),
+            "image/png".into()
+        ));
+        assert_err!(AttachmentFile::parse("logo".into(), "image/png".into()));
+        assert_ok!(AttachmentFile::parse(
+            "logo.jpeg".into(),
+            "image/jpeg".into()
+        ));
+    }
+
+    #[test]
+    fn binary_files_must_start_with_the_signature_of_their_type() {
+        let png = AttachmentFile::parse("logo.png".into(), "image/png".into()).unwrap();
+        assert_ok!(png.check_content(b"\x89PNG\r\n\x1a\nlogo"));
+        assert_err!(png.check_content(b"<script>alert(1)</script>"));
+        assert_err!(png.check_content(b""));
+
+        let gif = AttachmentFile::parse("logo.gif".into(), "image/gif".into()).unwrap();
+        assert_ok!(gif.check_content(b"GIF87alogo"));
+        assert_ok!(gif.check_content(b"GIF89alogo"));
+
+        let pdf = AttachmentFile::parse("report.pdf".into(), "application/pdf".into()).unwrap();
+        assert_err!(pdf.check_content(b"\xff\xd8\xffreport"));
+    }
+
+    #[test]
+    fn text_files_are_not_checked() {
+        let notes = AttachmentFile::parse("notes.txt".into(), "text/plain".into()).unwrap();
+        assert_ok!(notes.check_content(b"\x89PNG\r\n\x1a\n"));
+    }
+
+    #[test]
+    fn invalid_names_are_rejected() {
+        for name in ["", " ", "uploads/", "new\nline.png"] {
+            assert_err!(AttachmentFile::parse(name.into(), "image/png".into()));
+        }
+        assert_err!(AttachmentFile::parse(
+            format!("{}.png", "a".repeat(252)),
+            "image/png".into()
+        ));
+    }
+}
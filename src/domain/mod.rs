@@ -1,7 +1,19 @@
+mod attachment_file;
 mod new_subscriber;
+mod newsletter_body;
+mod newsletter_slug;
+mod newsletter_title;
 mod subscriber_email;
 mod subscriber_name;
+mod subscriber_tag;
+mod suppressed_address;
 
+pub use attachment_file::AttachmentFile;
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_body::NewsletterBody;
+pub use newsletter_slug::NewsletterSlug;
+pub use newsletter_title::NewsletterTitle;
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use subscriber_tag::SubscriberTag;
+pub use suppressed_address::SuppressedAddress;
@@ -1,8 +1,12 @@
-use crate::domain::{subscriber_email::SubscriberEmail, subscriber_name::SubscriberName};
+use crate::domain::{
+    subscriber_email::SubscriberEmail, subscriber_name::SubscriberName,
+    subscriber_tag::SubscriberTag,
+};
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    pub tags: Vec<SubscriberTag>,
 }
 
 #[cfg(test)]
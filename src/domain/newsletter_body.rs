@@ -0,0 +1,119 @@
+use crate::{html, merge_fields};
+
+const MAX_LENGTH: usize = 100_000;
+
+/// The plain text and HTML bodies of a newsletter issue. The HTML body is passed through an
+/// allow-list sanitiser, so it is safe to send and to display.
+#[derive(Debug)]
+pub struct NewsletterBody {
+    text: String,
+    html: String,
+}
+
+impl NewsletterBody {
+    pub fn parse(text: String, html: String) -> Result<NewsletterBody, String> {
+        let text = validate(text, "plain text")?;
+        let html = validate(html::sanitiser().clean(&html).to_string(), "HTML")?;
+
+        Ok(Self { text, html })
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn html(&self) -> &str {
+        &self.html
+    }
+}
+
+fn validate(content: String, kind: &str) -> Result<String, String> {
+    if content.trim().is_empty() {
+        Err(format!(
+            "The {} content of the issue cannot be empty.",
+            kind
+        ))
+    } else if content.chars().count() > MAX_LENGTH {
+        Err(format!(
+            "The {} content of the issue cannot be longer than {} characters.",
+            kind, MAX_LENGTH
+        ))
+    } else {
+        merge_fields::validate(&content)?;
+        Ok(content)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::NewsletterBody;
+
+    #[test]
+    fn empty_bodies_are_rejected() {
+        assert_err!(NewsletterBody::parse("".into(), "<p>Hello</p>".into()));
+        assert_err!(NewsletterBody::parse("Hello".into(), " ".into()));
+    }
+
+    #[test]
+    fn bodies_longer_than_the_limit_are_rejected() {
+        let text = "a".repeat(100_001);
+        assert_err!(NewsletterBody::parse(text, "<p>Hello</p>".into()));
+    }
+
+    #[test]
+    fn html_is_sanitised() {
+        let body = NewsletterBody::parse(
+            "Hello".into(),
+            r#"<p onclick="steal()">Hello</p><script>alert("xss")</script>"#.into(),
+        )
+        .unwrap();
+
+        assert_eq!("<p>Hello</p>", body.html());
+    }
+
+    #[test]
+    fn html_that_is_empty_once_sanitised_is_rejected() {
+        let html = "<script>alert(\"xss\")</script>".to_string();
+        assert_err!(NewsletterBody::parse("Hello".into(), html));
+    }
+
+    #[test]
+    fn bodies_with_unknown_merge_fields_are_rejected() {
+        assert_err!(NewsletterBody::parse(
+            "Hi {{nickname}}".into(),
+            "<p>Hello</p>".into()
+        ));
+    }
+
+    #[test]
+    fn merge_fields_survive_sanitisation() {
+        let body = NewsletterBody::parse(
+            "Hi {{name}}".into(),
+            r#"<p>Hi {{name}}, <a href="{{unsubscribe_url}}">unsubscribe</a></p>"#.into(),
+        )
+        .unwrap();
+
+        assert!(body.html().contains(r#"href="{{unsubscribe_url}}""#));
+    }
+
+    #[test]
+    fn inline_images_survive_sanitisation() {
+        let body = NewsletterBody::parse(
+            "Hello".into(),
+            r#"<p><img src="cid:3f2b8c1e-logo" alt="Logo"></p>"#.into(),
+        )
+        .unwrap();
+
+        assert!(body.html().contains(r#"src="cid:3f2b8c1e-logo""#));
+    }
+
+    #[test]
+    fn valid_bodies_are_parsed_successfully() {
+        assert_ok!(NewsletterBody::parse(
+            "Hello".into(),
+            r#"<p>Hello, <a href="https://example.com">world</a></p>"#.into()
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+const MAX_LENGTH: usize = 50;
+
+/// The identifier of a newsletter in URLs, e.g. `/newsletters/product-updates/subscriptions`.
+#[derive(Debug)]
+pub struct NewsletterSlug(String);
+
+impl NewsletterSlug {
+    pub fn parse(s: String) -> Result<NewsletterSlug, String> {
+        if s.is_empty()
+            || s.len() > MAX_LENGTH
+            || s.starts_with('-')
+            || s.ends_with('-')
+            || !s
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        {
+            Err(format!(
+                "{} is not a valid newsletter slug. Use up to {} lowercase letters, digits or '-'.",
+                s, MAX_LENGTH
+            ))
+        } else {
+            Ok(Self(s))
+        }
+    }
+}
+
+impl AsRef<str> for NewsletterSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::NewsletterSlug;
+
+    #[test]
+    fn empty_slugs_are_rejected() {
+        assert_err!(NewsletterSlug::parse("".into()));
+    }
+
+    #[test]
+    fn a_slug_longer_than_50_characters_is_rejected() {
+        assert_ok!(NewsletterSlug::parse("a".repeat(50)));
+        assert_err!(NewsletterSlug::parse("a".repeat(51)));
+    }
+
+    #[test]
+    fn slugs_containing_an_invalid_character_are_rejected() {
+        for slug in [
+            "Product",
+            "product updates",
+            "product/updates",
+            "-product",
+            "product-",
+        ] {
+            assert_err!(NewsletterSlug::parse(slug.into()));
+        }
+    }
+
+    #[test]
+    fn a_valid_slug_is_parsed_successfully() {
+        assert_ok!(NewsletterSlug::parse("product-updates-2".into()));
+    }
+}
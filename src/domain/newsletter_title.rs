@@ -0,0 +1,75 @@
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::merge_fields;
+
+const MAX_LENGTH: usize = 256;
+
+#[derive(Debug)]
+pub struct NewsletterTitle(String);
+
+impl NewsletterTitle {
+    pub fn parse(s: String) -> Result<NewsletterTitle, String> {
+        if s.trim().is_empty() {
+            Err("The title of the issue cannot be empty.".into())
+        } else if s.graphemes(true).count() > MAX_LENGTH {
+            Err(format!(
+                "The title of the issue cannot be longer than {} characters.",
+                MAX_LENGTH
+            ))
+        } else if s.chars().any(char::is_control) {
+            Err("The title of the issue must fit on a single line.".into())
+        } else {
+            merge_fields::validate(&s)?;
+            Ok(Self(s))
+        }
+    }
+}
+
+impl AsRef<str> for NewsletterTitle {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::NewsletterTitle;
+
+    #[test]
+    fn a_256_grapheme_long_title_is_valid() {
+        let title = "ë".repeat(256);
+        assert_ok!(NewsletterTitle::parse(title));
+    }
+
+    #[test]
+    fn a_title_longer_than_256_graphemes_is_rejected() {
+        let title = "a".repeat(257);
+        assert_err!(NewsletterTitle::parse(title));
+    }
+
+    #[test]
+    fn whitespace_only_titles_are_rejected() {
+        let title = " ".to_string();
+        assert_err!(NewsletterTitle::parse(title));
+    }
+
+    #[test]
+    fn titles_spanning_several_lines_are_rejected() {
+        let title = "First line\nSecond line".to_string();
+        assert_err!(NewsletterTitle::parse(title));
+    }
+
+    #[test]
+    fn titles_with_unknown_merge_fields_are_rejected() {
+        let title = "News for {{nickname}}".to_string();
+        assert_err!(NewsletterTitle::parse(title));
+    }
+
+    #[test]
+    fn a_valid_title_is_parsed_successfully() {
+        let title = "October news".to_string();
+        assert_ok!(NewsletterTitle::parse(title));
+    }
+}
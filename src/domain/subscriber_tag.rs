@@ -0,0 +1,87 @@
+const MAX_LENGTH: usize = 50;
+
+/// A label that groups subscribers into segments, e.g. `product-updates`. Tags are
+/// case-insensitive and stored in lowercase.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SubscriberTag(String);
+
+impl SubscriberTag {
+    pub fn parse(s: &str) -> Result<SubscriberTag, String> {
+        let tag = s.trim().to_lowercase();
+        if tag.is_empty()
+            || tag.len() > MAX_LENGTH
+            || !tag
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            Err(format!(
+                "{} is not a valid tag. Tags are up to {} letters, digits, '-' or '_'.",
+                s, MAX_LENGTH
+            ))
+        } else {
+            Ok(Self(tag))
+        }
+    }
+
+    /// Parses a comma-separated list of tags, ignoring duplicates and empty entries.
+    pub fn parse_list(s: &str) -> Result<Vec<SubscriberTag>, String> {
+        let mut tags = s
+            .split(',')
+            .filter(|tag| !tag.trim().is_empty())
+            .map(SubscriberTag::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+
+        Ok(tags)
+    }
+}
+
+impl AsRef<str> for SubscriberTag {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    use super::SubscriberTag;
+
+    #[test]
+    fn tags_are_normalised_to_lowercase() {
+        assert_ok_eq!(
+            SubscriberTag::parse(" Product-Updates "),
+            SubscriberTag("product-updates".into())
+        );
+    }
+
+    #[test]
+    fn empty_tags_are_rejected() {
+        assert_err!(SubscriberTag::parse(" "));
+    }
+
+    #[test]
+    fn a_tag_longer_than_50_characters_is_rejected() {
+        assert_ok!(SubscriberTag::parse(&"a".repeat(50)));
+        assert_err!(SubscriberTag::parse(&"a".repeat(51)));
+    }
+
+    #[test]
+    fn tags_containing_an_invalid_character_are_rejected() {
+        for tag in ["two words", "a,b", "<b>", "café"] {
+            assert_err!(SubscriberTag::parse(tag));
+        }
+    }
+
+    #[test]
+    fn lists_are_deduplicated() {
+        let tags = SubscriberTag::parse_list("news, events,,News").unwrap();
+
+        assert_eq!(
+            vec![SubscriberTag("events".into()), SubscriberTag("news".into())],
+            tags
+        );
+    }
+}
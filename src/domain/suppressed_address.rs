@@ -0,0 +1,88 @@
+use validator::ValidateEmail;
+
+use crate::domain::SubscriberEmail;
+
+const MAX_DOMAIN_LENGTH: usize = 253;
+
+/// An email address, or a domain to block every address at, e.g. `competitor.com`. It is
+/// stored in lowercase, as emails are matched regardless of case.
+#[derive(Debug)]
+pub struct SuppressedAddress(String);
+
+impl SuppressedAddress {
+    pub fn parse(s: String) -> Result<SuppressedAddress, String> {
+        let address = s.trim().to_lowercase();
+        let is_valid = if address.contains('@') {
+            ValidateEmail::validate_email(&address)
+        } else {
+            is_valid_domain(&address)
+        };
+
+        if is_valid {
+            Ok(Self(address))
+        } else {
+            Err(format!(
+                "{} is not a valid email address or domain.",
+                s.trim()
+            ))
+        }
+    }
+}
+
+impl From<&SubscriberEmail> for SuppressedAddress {
+    fn from(email: &SubscriberEmail) -> Self {
+        Self(email.as_ref().to_lowercase())
+    }
+}
+
+impl AsRef<str> for SuppressedAddress {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+fn is_valid_domain(domain: &str) -> bool {
+    domain.len() <= MAX_DOMAIN_LENGTH
+        && domain.contains('.')
+        && domain.split('.').all(|label| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use super::SuppressedAddress;
+
+    #[test]
+    fn emails_and_domains_are_stored_in_lowercase() {
+        let email = SuppressedAddress::parse(" Ursula@Example.com ".into()).unwrap();
+        assert_eq!("ursula@example.com", email.as_ref());
+
+        let domain = SuppressedAddress::parse("Competitor.COM".into()).unwrap();
+        assert_eq!("competitor.com", domain.as_ref());
+    }
+
+    #[test]
+    fn subdomains_are_accepted() {
+        assert_ok!(SuppressedAddress::parse("mail.competitor.co.uk".into()));
+    }
+
+    #[test]
+    fn invalid_addresses_are_rejected() {
+        for address in [
+            "",
+            "competitor",
+            "@competitor.com",
+            "competitor..com",
+            "-competitor.com",
+            "compet itor.com",
+        ] {
+            assert_err!(SuppressedAddress::parse(address.into()));
+        }
+    }
+}
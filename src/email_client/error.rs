@@ -0,0 +1,51 @@
+use std::fmt;
+
+use anyhow::anyhow;
+
+use crate::routes::error_chain_fmt;
+
+/// Why an email was not sent, classified by what the sender should do about it.
+#[derive(thiserror::Error)]
+pub enum EmailClientError {
+    /// The email may go through if it is sent again later, e.g. after a timeout or a server
+    /// error.
+    #[error("The email could not be sent right now.")]
+    Transient(#[source] anyhow::Error),
+    /// The recipient will never accept an email, e.g. because their address is inactive.
+    #[error("The email was rejected: {0}")]
+    Permanent(String),
+    /// This email will never be accepted, but the recipient is not known to be at fault, e.g.
+    /// the message is malformed or the provider refused it for a reason we do not recognise.
+    #[error("The email could not be sent: {0}")]
+    Rejected(String),
+    /// Too many emails were sent too quickly.
+    #[error("The email provider is rate limiting us: {0}")]
+    RateLimited(String),
+    /// The provider does not accept our credentials or sender, so nothing can be sent until
+    /// the configuration is fixed.
+    #[error("The email provider refused to send on our behalf: {0}")]
+    Unauthorized(String),
+}
+
+impl EmailClientError {
+    /// The error of a request that carried several emails, as reported for each of them. A
+    /// failure of the whole request does not blame any email in particular, so none of them are
+    /// dropped or have their recipient suppressed.
+    pub(super) fn for_each_email(&self) -> Self {
+        match self {
+            Self::Transient(e) => Self::Transient(anyhow!("{:#}", e)),
+            Self::Permanent(reason) | Self::Rejected(reason) => Self::Transient(anyhow!(
+                "The email provider refused a batch of emails: {}",
+                reason
+            )),
+            Self::RateLimited(reason) => Self::RateLimited(reason.clone()),
+            Self::Unauthorized(reason) => Self::Unauthorized(reason.clone()),
+        }
+    }
+}
+
+impl fmt::Debug for EmailClientError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
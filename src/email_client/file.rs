@@ -0,0 +1,90 @@
+use std::{fs, path::PathBuf};
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use lettre::{AsyncFileTransport, AsyncTransport, Tokio1Executor};
+
+use super::{smtp, EmailClientError, EmailMessage, EmailTransport};
+use crate::domain::SubscriberEmail;
+
+/// Writes every email to a `.eml` file instead of sending it, so that emails can be checked
+/// during development without an email provider.
+pub struct FileTransport {
+    transport: AsyncFileTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+}
+
+impl FileTransport {
+    /// Creates the directory if it does not exist yet.
+    pub fn new(directory: PathBuf, sender: SubscriberEmail) -> Result<Self, Error> {
+        fs::create_dir_all(&directory).with_context(|| {
+            format!(
+                "Failed to create the email directory {}.",
+                directory.display()
+            )
+        })?;
+
+        Ok(Self {
+            transport: AsyncFileTransport::new(directory),
+            sender,
+        })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FileTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_message(&self, message: &EmailMessage<'_>) -> Result<(), EmailClientError> {
+        let message = smtp::build_message(message)?;
+        let id = self
+            .transport
+            .send(message)
+            .await
+            .context("Failed to write the email to a file.")
+            .map_err(EmailClientError::Transient)?;
+        tracing::info!(email_id = %id, "Wrote an email to the email directory.");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use claims::assert_ok;
+    use uuid::Uuid;
+
+    use super::FileTransport;
+    use crate::{domain::SubscriberEmail, email_client::EmailTransport};
+
+    #[tokio::test]
+    async fn emails_are_written_to_the_directory() {
+        let directory = env::temp_dir().join(Uuid::new_v4().to_string());
+        let sender = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let transport = FileTransport::new(directory.clone(), sender).unwrap();
+
+        assert_ok!(
+            transport
+                .send_email(&recipient, "Welcome!", "<p>Hello</p>", "Hello")
+                .await
+        );
+
+        let files = fs::read_dir(&directory)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect::<Vec<_>>();
+        assert_eq!(1, files.len());
+        assert_eq!(Some("eml".as_ref()), files[0].extension());
+        let email = fs::read_to_string(&files[0]).unwrap();
+        assert!(email.contains("To: ursula@example.com"));
+        assert!(email.contains("Subject: Welcome!"));
+        assert!(email.contains("<p>Hello</p>"));
+
+        fs::remove_dir_all(directory).unwrap();
+    }
+}
@@ -0,0 +1,107 @@
+//! Sending emails. The transport is chosen in the configuration: Postmark's HTTP API, an SMTP
+//! relay, or a directory of `.eml` files for local development.
+
+mod error;
+mod file;
+mod postmark;
+mod smtp;
+
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::domain::SubscriberEmail;
+
+pub use error::EmailClientError;
+pub use file::FileTransport;
+pub use postmark::PostmarkTransport;
+pub use smtp::SmtpTransport;
+
+/// A single email, with its attachments.
+#[derive(Clone, Copy, Debug)]
+pub struct EmailMessage<'a> {
+    pub sender: &'a SubscriberEmail,
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_body: &'a str,
+    pub text_body: &'a str,
+    pub attachments: &'a [Attachment<'a>],
+}
+
+/// A file sent with an email.
+#[derive(Clone, Copy)]
+pub struct Attachment<'a> {
+    pub name: &'a str,
+    pub content_type: &'a str,
+    pub content: &'a [u8],
+    /// Set for images shown in the HTML body with `cid:{content_id}` rather than listed as
+    /// files.
+    pub content_id: Option<&'a str>,
+}
+
+impl fmt::Debug for Attachment<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Attachment")
+            .field("name", &self.name)
+            .field("content_type", &self.content_type)
+            .field("size", &self.content.len())
+            .field("content_id", &self.content_id)
+            .finish()
+    }
+}
+
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    /// The address emails are sent from by default.
+    fn sender(&self) -> &SubscriberEmail;
+
+    async fn send_message(&self, message: &EmailMessage<'_>) -> Result<(), EmailClientError>;
+
+    /// Sends an email from an address other than the configured sender, such as the address
+    /// of a newsletter.
+    async fn send_email_from(
+        &self,
+        sender: &SubscriberEmail,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+    ) -> Result<(), EmailClientError> {
+        self.send_message(&EmailMessage {
+            sender,
+            recipient,
+            subject,
+            html_body,
+            text_body,
+            attachments: &[],
+        })
+        .await
+    }
+
+    async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_body: &str,
+        text_body: &str,
+    ) -> Result<(), EmailClientError> {
+        self.send_email_from(self.sender(), recipient, subject, html_body, text_body)
+            .await
+    }
+
+    /// Sends several emails, returning the outcome of each one in order. An error for the whole
+    /// batch means that none of the emails were sent. Transports without a batch API send the
+    /// emails one by one, and transports that split the batch into several requests report the
+    /// error of a failed request for each of the emails it left unsent.
+    async fn send_email_batch(
+        &self,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<(), EmailClientError>>, EmailClientError> {
+        let mut results = Vec::with_capacity(messages.len());
+        for message in messages {
+            results.push(self.send_message(message).await);
+        }
+
+        Ok(results)
+    }
+}
@@ -0,0 +1,761 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use reqwest::{Client, Response, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+
+use super::{Attachment, EmailClientError, EmailMessage, EmailTransport};
+use crate::domain::SubscriberEmail;
+
+/// The most messages Postmark accepts in a single batch request.
+const MAX_BATCH_SIZE: usize = 500;
+/// The largest batch request Postmark accepts, in bytes.
+const MAX_BATCH_PAYLOAD_SIZE: usize = 50 * 1024 * 1024;
+/// Postmark error codes for problems with the account or the sender signature, which affect
+/// every email until the configuration is fixed.
+const ACCOUNT_ERROR_CODES: &[i64] = &[10, 400, 401, 405, 412];
+/// Postmark's error code for recipients marked as inactive after a bounce or spam complaint.
+const INACTIVE_RECIPIENT_ERROR_CODE: i64 = 406;
+/// Postmark's error code for invalid requests. It only blames the recipient when its message
+/// names their address.
+const INVALID_REQUEST_ERROR_CODE: i64 = 300;
+/// Postmark's error code while it is down for maintenance.
+const MAINTENANCE_ERROR_CODE: i64 = 100;
+
+/// Sends emails through Postmark's HTTP API.
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    sender: SubscriberEmail,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: String,
+        sender: SubscriberEmail,
+        authorization_token: Secret<String>,
+        timeout: Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self {
+            http_client,
+            base_url,
+            sender,
+            authorization_token,
+        }
+    }
+
+    /// Sends a batch that fits in a single request.
+    async fn send_batch_request(
+        &self,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<(), EmailClientError>>, EmailClientError> {
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body = messages
+            .iter()
+            .map(SendEmailRequest::from)
+            .collect::<Vec<_>>();
+
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| EmailClientError::Transient(e.into()))?;
+        let responses: Vec<PostmarkResponse> = check_status(response, None)
+            .await?
+            .json()
+            .await
+            .map_err(|e| EmailClientError::Transient(e.into()))?;
+        if responses.len() != messages.len() {
+            return Err(EmailClientError::Transient(anyhow!(
+                "Postmark answered a batch of {} emails with {} results.",
+                messages.len(),
+                responses.len()
+            )));
+        }
+
+        // Each result is classified as if the message had been sent on its own.
+        Ok(messages
+            .iter()
+            .zip(responses)
+            .map(|(message, response)| match response.error_code {
+                0 => Ok(()),
+                _ => Err(classify(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    Some(response),
+                    Some(message.recipient),
+                )),
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_message(&self, message: &EmailMessage<'_>) -> Result<(), EmailClientError> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest::from(message);
+
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(&request_body)
+            .send()
+            .await
+            .map_err(|e| EmailClientError::Transient(e.into()))?;
+        check_status(response, Some(message.recipient)).await?;
+
+        Ok(())
+    }
+
+    /// Uses the batch endpoint, which reports the outcome of every message separately.
+    async fn send_email_batch(
+        &self,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<Vec<Result<(), EmailClientError>>, EmailClientError> {
+        let mut results = Vec::with_capacity(messages.len());
+
+        for chunk in batches(messages) {
+            match self.send_batch_request(chunk).await {
+                Ok(chunk_results) => results.extend(chunk_results),
+                Err(e) if results.is_empty() => return Err(e),
+                // The emails of the earlier requests went out, so only the rest may be sent
+                // again.
+                Err(e) => {
+                    let unsent = messages.len() - results.len();
+                    results.extend((0..unsent).map(|_| Err(e.for_each_email())));
+                    break;
+                }
+            }
+        }
+
+        Ok(results)
+    }
+}
+
+/// Splits messages into batches that stay under Postmark's limits. Messages with large
+/// attachments make for fewer messages per batch.
+fn batches<'m, 'a>(messages: &'m [EmailMessage<'a>]) -> Vec<&'m [EmailMessage<'a>]> {
+    let mut batches = Vec::new();
+    let (mut start, mut payload_size) = (0, 0);
+    for (i, message) in messages.iter().enumerate() {
+        let size = payload_size_estimate(message);
+        if i > start
+            && (i - start == MAX_BATCH_SIZE || payload_size + size > MAX_BATCH_PAYLOAD_SIZE)
+        {
+            batches.push(&messages[start..i]);
+            (start, payload_size) = (i, 0);
+        }
+        payload_size += size;
+    }
+    if start < messages.len() {
+        batches.push(&messages[start..]);
+    }
+
+    batches
+}
+
+/// Attachments grow by a third once encoded in base64. A little room is left for the other
+/// fields and the JSON around them.
+fn payload_size_estimate(message: &EmailMessage<'_>) -> usize {
+    let attachments_size: usize = message
+        .attachments
+        .iter()
+        .map(|attachment| attachment.content.len().div_ceil(3) * 4 + attachment.name.len())
+        .sum();
+
+    message.subject.len()
+        + message.html_body.len()
+        + message.text_body.len()
+        + attachments_size
+        + 1024
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    attachments: Vec<PostmarkAttachment<'a>>,
+}
+
+impl<'a> From<&EmailMessage<'a>> for SendEmailRequest<'a> {
+    fn from(message: &EmailMessage<'a>) -> Self {
+        Self {
+            from: message.sender.as_ref(),
+            to: message.recipient.as_ref(),
+            subject: message.subject,
+            html_body: message.html_body,
+            text_body: message.text_body,
+            attachments: message
+                .attachments
+                .iter()
+                .map(PostmarkAttachment::from)
+                .collect(),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkAttachment<'a> {
+    name: &'a str,
+    /// The content encoded in base64.
+    content: String,
+    content_type: &'a str,
+    /// Postmark expects inline images to be identified as `cid:{content_id}`.
+    #[serde(rename = "ContentID", skip_serializing_if = "Option::is_none")]
+    content_id: Option<String>,
+}
+
+impl<'a> From<&Attachment<'a>> for PostmarkAttachment<'a> {
+    fn from(attachment: &Attachment<'a>) -> Self {
+        Self {
+            name: attachment.name,
+            content: STANDARD.encode(attachment.content),
+            content_type: attachment.content_type,
+            content_id: attachment
+                .content_id
+                .map(|content_id| format!("cid:{}", content_id)),
+        }
+    }
+}
+
+/// The body of Postmark's error responses, and of each result of a batch request.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkResponse {
+    error_code: i64,
+    message: String,
+}
+
+/// `recipient` is the recipient of the message the response is about, if there is only one.
+async fn check_status(
+    response: Response,
+    recipient: Option<&SubscriberEmail>,
+) -> Result<Response, EmailClientError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+
+    let body = response.json::<PostmarkResponse>().await.ok();
+    Err(classify(status, body, recipient))
+}
+
+/// Only errors that are known to be about the recipient are permanent, as the worker
+/// suppresses the recipients of permanent errors.
+fn classify(
+    status: StatusCode,
+    body: Option<PostmarkResponse>,
+    recipient: Option<&SubscriberEmail>,
+) -> EmailClientError {
+    let names_recipient = match (&body, recipient) {
+        (Some(body), Some(recipient)) => body.message.contains(recipient.as_ref()),
+        _ => false,
+    };
+    let error_code = body.as_ref().map(|b| b.error_code);
+    let description = match body {
+        Some(body) => format!("Postmark error {}: {}", body.error_code, body.message),
+        None => format!("Postmark answered with {}.", status),
+    };
+
+    match error_code {
+        Some(code) if ACCOUNT_ERROR_CODES.contains(&code) => {
+            EmailClientError::Unauthorized(description)
+        }
+        Some(INACTIVE_RECIPIENT_ERROR_CODE) => EmailClientError::Permanent(description),
+        Some(INVALID_REQUEST_ERROR_CODE) if names_recipient => {
+            EmailClientError::Permanent(description)
+        }
+        Some(MAINTENANCE_ERROR_CODE) => EmailClientError::Transient(anyhow!(description)),
+        _ if status == StatusCode::UNAUTHORIZED => EmailClientError::Unauthorized(description),
+        _ if status == StatusCode::TOO_MANY_REQUESTS => EmailClientError::RateLimited(description),
+        _ if status.is_client_error() => EmailClientError::Rejected(description),
+        _ => EmailClientError::Transient(anyhow!(description)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use claims::{assert_err, assert_ok};
+    use fake::{
+        faker::{
+            internet::en::SafeEmail,
+            lorem::en::{Paragraph, Sentence},
+        },
+        Fake, Faker,
+    };
+    use secrecy::Secret;
+    use serde_json::{json, Value};
+    use wiremock::{matchers, Mock, MockServer, ResponseTemplate};
+
+    use super::{batches, PostmarkTransport};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{Attachment, EmailClientError, EmailMessage, EmailTransport},
+    };
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &wiremock::Request) -> bool {
+            let result: Result<Value, _> = serde_json::from_slice(&request.body);
+            if let Ok(body) = result {
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> PostmarkTransport {
+        PostmarkTransport::new(
+            base_url,
+            email(),
+            Secret::new(Faker.fake()),
+            Duration::from_millis(200),
+        )
+    }
+
+    /// Sends an email to a server that answers with `response`.
+    async fn send_email_answered_with(response: ResponseTemplate) -> Result<(), EmailClientError> {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(matchers::any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expected_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(matchers::header_exists("X-Postmark-Server-Token"))
+            .and(matchers::header("Content-Type", "application/json"))
+            .and(matchers::path("/email"))
+            .and(matchers::method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        assert!(matches!(outcome, Err(EmailClientError::Transient(_))));
+    }
+
+    #[tokio::test]
+    async fn inactive_recipients_are_a_permanent_error() {
+        let response = ResponseTemplate::new(422).set_body_json(json!({
+            "ErrorCode": 406,
+            "Message": "You tried to send to a recipient that has been marked as inactive."
+        }));
+
+        let outcome = send_email_answered_with(response).await;
+        assert!(matches!(outcome, Err(EmailClientError::Permanent(_))));
+    }
+
+    #[tokio::test]
+    async fn invalid_requests_are_only_permanent_when_they_name_the_recipient() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipient = email();
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(422).set_body_json(json!({
+                "ErrorCode": 300,
+                "Message": format!("Error parsing 'To': Illegal email address '{}'.", recipient.as_ref())
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        let outcome = email_client
+            .send_email(&recipient, &subject(), &content(), &content())
+            .await;
+        assert!(matches!(outcome, Err(EmailClientError::Permanent(_))));
+
+        let response = ResponseTemplate::new(422).set_body_json(json!({
+            "ErrorCode": 300,
+            "Message": "Invalid 'From' address."
+        }));
+        let outcome = send_email_answered_with(response).await;
+        assert!(matches!(outcome, Err(EmailClientError::Rejected(_))));
+    }
+
+    #[tokio::test]
+    async fn unknown_client_errors_are_rejected_without_blaming_the_recipient() {
+        let unknown_code = ResponseTemplate::new(422).set_body_json(json!({
+            "ErrorCode": 1234,
+            "Message": "Something new"
+        }));
+
+        for response in [unknown_code, ResponseTemplate::new(400)] {
+            let outcome = send_email_answered_with(response).await;
+            assert!(matches!(outcome, Err(EmailClientError::Rejected(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn invalid_credentials_are_an_unauthorized_error() {
+        let bad_token = ResponseTemplate::new(401).set_body_json(json!({
+            "ErrorCode": 10,
+            "Message": "Bad or missing API token"
+        }));
+        let unconfirmed_sender = ResponseTemplate::new(422).set_body_json(json!({
+            "ErrorCode": 401,
+            "Message": "Sender signature not confirmed"
+        }));
+
+        for response in [bad_token, unconfirmed_sender] {
+            let outcome = send_email_answered_with(response).await;
+            assert!(matches!(outcome, Err(EmailClientError::Unauthorized(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn too_many_requests_are_a_rate_limited_error() {
+        let outcome = send_email_answered_with(ResponseTemplate::new(429)).await;
+        assert!(matches!(outcome, Err(EmailClientError::RateLimited(_))));
+    }
+
+    #[tokio::test]
+    async fn maintenance_is_a_transient_error() {
+        let response = ResponseTemplate::new(503).set_body_json(json!({
+            "ErrorCode": 100,
+            "Message": "Maintenance"
+        }));
+
+        let outcome = send_email_answered_with(response).await;
+        assert!(matches!(outcome, Err(EmailClientError::Transient(_))));
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        let response = ResponseTemplate::new(200).set_delay(Duration::from_secs(180));
+        Mock::given(matchers::any())
+            .respond_with(response)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        assert!(matches!(outcome, Err(EmailClientError::Transient(_))));
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_sends_every_message_in_one_request() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (sender, recipient) = (email(), email());
+        let (subject, content) = (subject(), content());
+        let message = EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: &subject,
+            html_body: &content,
+            text_body: &content,
+            attachments: &[],
+        };
+
+        Mock::given(matchers::header_exists("X-Postmark-Server-Token"))
+            .and(matchers::path("/email/batch"))
+            .and(matchers::method("POST"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 0, "Message": "OK" }
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email_batch(&[message, message])
+            .await
+            .unwrap();
+        assert_eq!(2, outcome.len());
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(2, body.len());
+        assert_eq!(recipient.as_ref(), body[0]["To"]);
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_reports_the_outcome_of_each_message() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (sender, recipient) = (email(), email());
+        let message = EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Subject",
+            html_body: "<p>Content</p>",
+            text_body: "Content",
+            attachments: &[],
+        };
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+                { "ErrorCode": 0, "Message": "OK" },
+                { "ErrorCode": 406, "Message": "Inactive recipient" },
+                { "ErrorCode": 300, "Message": "Invalid 'From' address." }
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_email_batch(&[message, message, message])
+            .await
+            .unwrap();
+        assert_ok!(&outcome[0]);
+        assert!(matches!(&outcome[1], Err(EmailClientError::Permanent(_))));
+        assert!(matches!(&outcome[2], Err(EmailClientError::Rejected(_))));
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_fails_if_the_server_returns_500() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (sender, recipient) = (email(), email());
+        let message = EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Subject",
+            html_body: "<p>Content</p>",
+            text_body: "Content",
+            attachments: &[],
+        };
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client.send_email_batch(&[message]).await;
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_batch_only_fails_the_messages_left_unsent_by_a_failed_request() {
+        let (sender, recipient) = (email(), email());
+        let message = EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Subject",
+            html_body: "<p>Content</p>",
+            text_body: "Content",
+            attachments: &[],
+        };
+        let messages = [message; 501];
+
+        for status in [500, 429] {
+            let mock_server = MockServer::start().await;
+            let email_client = email_client(mock_server.uri());
+            Mock::given(matchers::any())
+                .respond_with(
+                    ResponseTemplate::new(200)
+                        .set_body_json(vec![json!({ "ErrorCode": 0, "Message": "OK" }); 500]),
+                )
+                .up_to_n_times(1)
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+            Mock::given(matchers::any())
+                .respond_with(ResponseTemplate::new(status))
+                .expect(1)
+                .mount(&mock_server)
+                .await;
+
+            let outcome = email_client.send_email_batch(&messages).await.unwrap();
+            assert_eq!(501, outcome.len());
+            assert!(outcome[..500].iter().all(Result::is_ok));
+            match status {
+                500 => assert!(matches!(outcome[500], Err(EmailClientError::Transient(_)))),
+                _ => assert!(matches!(
+                    outcome[500],
+                    Err(EmailClientError::RateLimited(_))
+                )),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn attachments_are_sent_encoded_in_base64() {
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let (sender, recipient) = (email(), email());
+        let attachments = [
+            Attachment {
+                name: "logo.png",
+                content_type: "image/png",
+                content: b"logo",
+                content_id: Some("logo-id"),
+            },
+            Attachment {
+                name: "report.pdf",
+                content_type: "application/pdf",
+                content: b"report",
+                content_id: None,
+            },
+        ];
+
+        Mock::given(matchers::any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let outcome = email_client
+            .send_message(&EmailMessage {
+                sender: &sender,
+                recipient: &recipient,
+                subject: "Subject",
+                html_body: r#"<img src="cid:logo-id">"#,
+                text_body: "Content",
+                attachments: &attachments,
+            })
+            .await;
+        assert_ok!(outcome);
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            json!([
+                {
+                    "Name": "logo.png",
+                    "Content": "bG9nbw==",
+                    "ContentType": "image/png",
+                    "ContentID": "cid:logo-id"
+                },
+                {
+                    "Name": "report.pdf",
+                    "Content": "cmVwb3J0",
+                    "ContentType": "application/pdf"
+                }
+            ]),
+            body["Attachments"]
+        );
+    }
+
+    #[test]
+    fn batches_stay_under_the_payload_limit() {
+        let (sender, recipient) = (email(), email());
+        let content = vec![0; 20 * 1024 * 1024];
+        let attachments = [Attachment {
+            name: "large.pdf",
+            content_type: "application/pdf",
+            content: &content,
+            content_id: None,
+        }];
+        let message = EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Subject",
+            html_body: "<p>Content</p>",
+            text_body: "Content",
+            attachments: &attachments,
+        };
+
+        // Each message takes up more than half of the limit once encoded.
+        let batch_sizes: Vec<_> = batches(&[message; 5]).iter().map(|b| b.len()).collect();
+        assert_eq!(vec![1, 1, 1, 1, 1], batch_sizes);
+
+        let message = EmailMessage {
+            attachments: &[],
+            ..message
+        };
+        let batch_sizes: Vec<_> = batches(&[message; 1200]).iter().map(|b| b.len()).collect();
+        assert_eq!(vec![500, 500, 200], batch_sizes);
+    }
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use anyhow::{Context, Error};
+use async_trait::async_trait;
+use lettre::{
+    message::{self, header::ContentType, Mailbox, MultiPart, SinglePart},
+    transport::smtp::{self, authentication::Credentials},
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+
+use super::{Attachment, EmailClientError, EmailMessage, EmailTransport};
+use crate::domain::SubscriberEmail;
+
+/// SMTP replies for rejected credentials.
+const AUTHENTICATION_REPLY_CODES: &[u16] = &[530, 534, 535];
+/// SMTP replies for mailboxes that do not exist or do not accept emails.
+const RECIPIENT_REPLY_CODES: &[u16] = &[550, 551, 553];
+
+/// Sends emails through an SMTP relay. The connection is upgraded with STARTTLS before the
+/// credentials are sent.
+pub struct SmtpTransport {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    sender: SubscriberEmail,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        host: &str,
+        port: u16,
+        username: String,
+        password: Secret<String>,
+        sender: SubscriberEmail,
+        timeout: Duration,
+    ) -> Result<Self, Error> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)
+            .context("Failed to configure the SMTP relay.")?
+            .port(port)
+            .credentials(Credentials::new(
+                username,
+                password.expose_secret().to_owned(),
+            ))
+            .timeout(Some(timeout))
+            .build();
+
+        Ok(Self { transport, sender })
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    fn sender(&self) -> &SubscriberEmail {
+        &self.sender
+    }
+
+    async fn send_message(&self, message: &EmailMessage<'_>) -> Result<(), EmailClientError> {
+        let message = build_message(message)?;
+        self.transport.send(message).await.map_err(classify)?;
+
+        Ok(())
+    }
+}
+
+fn classify(error: smtp::Error) -> EmailClientError {
+    let code = error.status().map(u16::from);
+    if code.is_some_and(|code| AUTHENTICATION_REPLY_CODES.contains(&code)) {
+        EmailClientError::Unauthorized(error.to_string())
+    } else if code.is_some_and(|code| RECIPIENT_REPLY_CODES.contains(&code)) {
+        EmailClientError::Permanent(error.to_string())
+    } else if error.is_permanent() {
+        EmailClientError::Rejected(error.to_string())
+    } else {
+        EmailClientError::Transient(anyhow::Error::new(error).context("The SMTP relay failed."))
+    }
+}
+
+/// A MIME message with both bodies, leaving the choice to the recipient's email client. Inline
+/// images are related to the bodies, and other attachments follow them. Messages that cannot be
+/// built are rejected, without blaming the recipient.
+pub(super) fn build_message(message: &EmailMessage<'_>) -> Result<Message, EmailClientError> {
+    let mut body = MultiPart::alternative_plain_html(
+        message.text_body.to_owned(),
+        message.html_body.to_owned(),
+    );
+    let (inline, files): (Vec<_>, Vec<_>) = message
+        .attachments
+        .iter()
+        .partition(|attachment| attachment.content_id.is_some());
+    if !inline.is_empty() {
+        body = inline.into_iter().try_fold(
+            MultiPart::related().multipart(body),
+            |related, attachment| {
+                Ok::<_, EmailClientError>(related.singlepart(attachment_part(attachment)?))
+            },
+        )?;
+    }
+    if !files.is_empty() {
+        body = files.into_iter().try_fold(
+            MultiPart::mixed().multipart(body),
+            |mixed, attachment| {
+                Ok::<_, EmailClientError>(mixed.singlepart(attachment_part(attachment)?))
+            },
+        )?;
+    }
+
+    Message::builder()
+        .from(mailbox(message.sender)?)
+        .to(mailbox(message.recipient)?)
+        .subject(message.subject)
+        .multipart(body)
+        .map_err(|e| EmailClientError::Rejected(format!("Failed to build the email: {}", e)))
+}
+
+fn attachment_part(attachment: &Attachment<'_>) -> Result<SinglePart, EmailClientError> {
+    let content_type = ContentType::parse(attachment.content_type).map_err(|_| {
+        EmailClientError::Rejected(format!(
+            "{} is not a valid content type.",
+            attachment.content_type
+        ))
+    })?;
+    let part = match attachment.content_id {
+        Some(content_id) => message::Attachment::new_inline(content_id.to_owned()),
+        None => message::Attachment::new(attachment.name.to_owned()),
+    };
+
+    Ok(part.body(attachment.content.to_vec(), content_type))
+}
+
+fn mailbox(email: &SubscriberEmail) -> Result<Mailbox, EmailClientError> {
+    email.as_ref().parse().map_err(|_| {
+        EmailClientError::Rejected(format!("{} is not a valid mailbox.", email.as_ref()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_ok;
+
+    use super::build_message;
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{Attachment, EmailMessage},
+    };
+
+    #[test]
+    fn messages_carry_both_bodies() {
+        let sender = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+
+        let message = build_message(&EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Welcome!",
+            html_body: "<p>Hello</p>",
+            text_body: "Hello",
+            attachments: &[],
+        });
+        assert_ok!(&message);
+
+        let formatted = String::from_utf8(message.unwrap().formatted()).unwrap();
+        assert!(formatted.contains("From: sender@example.com"));
+        assert!(formatted.contains("multipart/alternative"));
+        assert!(formatted.contains("text/plain"));
+        assert!(formatted.contains("text/html"));
+    }
+
+    #[test]
+    fn messages_carry_inline_images_and_attachments() {
+        let sender = SubscriberEmail::parse("sender@example.com".into()).unwrap();
+        let recipient = SubscriberEmail::parse("ursula@example.com".into()).unwrap();
+        let attachments = [
+            Attachment {
+                name: "logo.png",
+                content_type: "image/png",
+                content: b"not really a png",
+                content_id: Some("logo-id"),
+            },
+            Attachment {
+                name: "report.pdf",
+                content_type: "application/pdf",
+                content: b"not really a pdf",
+                content_id: None,
+            },
+        ];
+
+        let message = build_message(&EmailMessage {
+            sender: &sender,
+            recipient: &recipient,
+            subject: "Issue #1",
+            html_body: r#"<img src="cid:logo-id">"#,
+            text_body: "Hello",
+            attachments: &attachments,
+        });
+        assert_ok!(&message);
+
+        let formatted = String::from_utf8(message.unwrap().formatted()).unwrap();
+        assert!(formatted.contains("multipart/mixed"));
+        assert!(formatted.contains("multipart/related"));
+        assert!(formatted.contains("Content-ID: <logo-id>"));
+        assert!(formatted.contains("Content-Disposition: attachment; filename=\"report.pdf\""));
+    }
+}
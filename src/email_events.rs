@@ -0,0 +1,55 @@
+use anyhow::{Context, Error};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EmailEventKind {
+    Bounce,
+    SpamComplaint,
+}
+
+impl EmailEventKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EmailEventKind::Bounce => "bounce",
+            EmailEventKind::SpamComplaint => "spam_complaint",
+        }
+    }
+}
+
+/// A bounce or spam complaint reported by the email provider.
+#[derive(Debug)]
+pub struct EmailEvent<'a> {
+    pub provider_event_id: i64,
+    pub kind: EmailEventKind,
+    pub bounce_type: Option<&'a str>,
+    pub email: &'a str,
+    pub description: Option<&'a str>,
+}
+
+/// Stores the event, returning `false` if it was already recorded.
+#[tracing::instrument(name = "Record email event", skip(executor))]
+pub async fn record_email_event(
+    event: &EmailEvent<'_>,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, Error> {
+    let n_inserted_rows = sqlx::query!(
+        r#"INSERT INTO email_events (
+            event_id, provider_event_id, kind, bounce_type, email, description, received_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, now())
+        ON CONFLICT (provider_event_id) DO NOTHING"#,
+        Uuid::new_v4(),
+        event.provider_event_id,
+        event.kind.as_str(),
+        event.bounce_type,
+        event.email,
+        event.description
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record an email event.")?
+    .rows_affected();
+
+    Ok(n_inserted_rows > 0)
+}
@@ -0,0 +1,41 @@
+use actix_web::{http::header::ContentType, Error, HttpResponse};
+use askama::Template;
+
+use crate::utils;
+
+/// Renders a page template. Values are escaped by the template engine unless a template opts
+/// out explicitly with the `safe` filter.
+pub fn render(template: &impl Template) -> Result<HttpResponse, Error> {
+    let body = template.render().map_err(utils::e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(body))
+}
+
+/// The allow-list sanitiser for HTML written by admins. `cid:` URLs are allowed on top of the
+/// defaults, so that issues can show the images sent inline with them.
+pub fn sanitiser() -> ammonia::Builder<'static> {
+    let mut sanitiser = ammonia::Builder::default();
+    sanitiser.add_url_schemes(["cid"]);
+
+    sanitiser
+}
+
+/// Escapes text for use in HTML built outside of templates, such as email bodies. The output
+/// is safe both in text and in quoted attributes.
+pub fn escape(value: &str) -> String {
+    let mut output = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#x27;"),
+            _ => output.push(c),
+        }
+    }
+
+    output
+}
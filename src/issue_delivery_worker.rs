@@ -1,142 +1,533 @@
-use std::time::Duration;
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    sync::Arc,
+    time::Duration,
+};
 
 use anyhow::Error;
+use chrono::Utc;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use tokio::time;
-use tracing::{field, Span};
+use tracing::Span;
 use uuid::Uuid;
 
-use crate::{configuration::Settings, domain::SubscriberEmail, email_client::EmailClient, startup};
+use crate::{
+    attachments::{self, IssueAttachment},
+    configuration::Settings,
+    domain::{SubscriberEmail, SuppressedAddress},
+    email_client::{Attachment, EmailClientError, EmailMessage, EmailTransport},
+    merge_fields::{self, MergeValues},
+    send_rate::{Permit, SendRateLimiter},
+    startup,
+    suppressions::{self, SuppressionSource},
+    tracking,
+};
 
 type PgTransaction = Transaction<'static, Postgres>;
 
+/// The number of tasks dequeued at once, which is also the largest batch Postmark accepts.
+const BATCH_SIZE: i64 = 500;
+/// Failed deliveries are dropped after this many retries.
+const MAX_RETRIES: i16 = 5;
+/// The delay before the first retry, doubled for every further one.
+const RETRY_DELAY_SECONDS: i64 = 60;
+
+struct Task {
+    newsletter_issue_id: Uuid,
+    subscriber_email: String,
+    n_retries: i16,
+    /// The address was added to the suppression list after the issue was queued.
+    suppressed: bool,
+}
+
 struct NewsletterIssue {
+    newsletter_id: Uuid,
+    sender_email: Option<String>,
+    title: String,
+    text_content: String,
+    html_content: String,
+    tracking_enabled: bool,
+    /// The links rewritten for click tracking, empty unless tracking is enabled.
+    tracked_links: Vec<String>,
+    attachments: Vec<IssueAttachment>,
+}
+
+/// An issue with the merge fields filled in for a single recipient.
+struct PersonalisedIssue {
+    sender: Option<SubscriberEmail>,
     title: String,
     text_content: String,
     html_content: String,
 }
 
+/// A task with its issue ready to be sent.
+struct Delivery {
+    task: Task,
+    recipient: SubscriberEmail,
+    issue: PersonalisedIssue,
+}
+
+struct Subscriber {
+    id: Uuid,
+    email: String,
+    name: String,
+    unsubscribe_token: String,
+}
+
 pub enum ExecutionOutcome {
     TaskCompleted,
     EmptyQueue,
+    /// The send rate is used up. Nothing else can be sent before this much time has passed.
+    Throttled(Duration),
 }
 
 pub async fn run_worker_until_stopped(configuration: Settings) -> Result<(), Error> {
     let db_pool = startup::get_db_pool(&configuration.database);
-    let email_client = configuration.email_client.client();
+    let email_client = configuration.email_client.client()?;
+    let rate_limiter = configuration.delivery.rate_limiter();
 
-    worker_loop(db_pool, email_client).await
+    worker_loop(
+        db_pool,
+        email_client,
+        rate_limiter,
+        configuration.application.base_url,
+    )
+    .await
 }
 
-#[tracing::instrument(
-    skip_all,
-    fields(
-        newsletter_issue_id=tracing::field::Empty,
-        subscriber_email=tracing::field::Empty
-    )
-)]
+#[tracing::instrument(skip_all, fields(n_tasks = tracing::field::Empty))]
 pub async fn try_execute_task(
     pool: &PgPool,
-    email_client: &EmailClient,
+    email_client: &dyn EmailTransport,
+    rate_limiter: &SendRateLimiter,
+    base_url: &str,
 ) -> Result<ExecutionOutcome, Error> {
-    let task = dequeue_task(pool).await?;
-    if task.is_none() {
+    let Some((mut transaction, mut tasks)) = dequeue_tasks(pool).await? else {
         return Ok(ExecutionOutcome::EmptyQueue);
+    };
+    // The tasks beyond the permit stay in the queue for the next round.
+    match rate_limiter.acquire(pool, tasks.len()).await? {
+        Permit::Granted(n_tasks) => tasks.truncate(n_tasks),
+        Permit::Wait(delay) => {
+            transaction.rollback().await?;
+            return Ok(ExecutionOutcome::Throttled(delay));
+        }
     }
-    let (transaction, issue_id, email) = task.unwrap();
-
-    Span::current()
-        .record("newsletter_issue_id", &field::display(issue_id))
-        .record("subscriber_email", &field::display(&email));
-
-    match SubscriberEmail::parse(email.clone()) {
-        Ok(email) => {
-            let issue = get_issue(pool, issue_id).await?;
-            if let Err(e) = email_client
-                .send_email(
-                    &email,
-                    &issue.title,
-                    &issue.html_content,
-                    &issue.text_content,
-                )
-                .await
-            {
-                tracing::error!(error.cause_chain = ?e, error.message = %e, "Failed to deliver issue to a confirmed subscriber. Skipping.");
+    Span::current().record("n_tasks", tasks.len());
+
+    // Issues and subscribers are loaded once for the whole batch.
+    let mut issues = HashMap::new();
+    for task in &tasks {
+        if let Entry::Vacant(entry) = issues.entry(task.newsletter_issue_id) {
+            entry.insert(get_issue(pool, task.newsletter_issue_id, base_url).await?);
+        }
+    }
+    let mut subscribers = HashMap::new();
+    for (newsletter_id, emails) in group_by_newsletter(&tasks, &issues) {
+        subscribers.insert(
+            newsletter_id,
+            get_subscribers(pool, newsletter_id, &emails).await?,
+        );
+    }
+
+    let mut deliveries = Vec::with_capacity(tasks.len());
+    for task in tasks {
+        if task.suppressed {
+            tracing::info!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                "Skipping a subscriber on the suppression list."
+            );
+            delete_task(&mut transaction, &task).await?;
+            continue;
+        }
+        let recipient = match SubscriberEmail::parse(task.subscriber_email.clone()) {
+            Ok(recipient) => recipient,
+            Err(e) => {
+                tracing::error!(
+                    newsletter_issue_id = %task.newsletter_issue_id,
+                    subscriber_email = %task.subscriber_email,
+                    error.message = %e,
+                    "Skipping a confirmed subscriber. Their stored contact details are invalid."
+                );
+                delete_task(&mut transaction, &task).await?;
+                continue;
+            }
+        };
+        let issue = &issues[&task.newsletter_issue_id];
+        let subscriber = subscribers
+            .get(&issue.newsletter_id)
+            .and_then(|subscribers| subscribers.get(&task.subscriber_email));
+        let subscriber_id = subscriber.map(|s| s.id);
+        match personalise(issue, subscriber, &recipient, base_url) {
+            Ok(mut personalised) => {
+                if let (true, Some(subscriber_id)) = (issue.tracking_enabled, subscriber_id) {
+                    let token = tracking::tracking_token(
+                        &mut transaction,
+                        task.newsletter_issue_id,
+                        subscriber_id,
+                    )
+                    .await?;
+                    personalised.html_content = tracking::add_tracking(
+                        &personalised.html_content,
+                        &issue.tracked_links,
+                        &token,
+                        base_url,
+                    );
+                }
+                deliveries.push(Delivery {
+                    task,
+                    recipient,
+                    issue: personalised,
+                })
+            }
+            Err(e) => {
+                tracing::error!(
+                    newsletter_issue_id = %task.newsletter_issue_id,
+                    subscriber_email = %task.subscriber_email,
+                    error.message = %e,
+                    "Skipping a confirmed subscriber. The issue could not be personalised for them."
+                );
+                delete_task(&mut transaction, &task).await?;
             }
         }
-        Err(e) => {
-            tracing::error!(error.cause_chain = ?e, error.message = %e, "Skipping to a confirmed subscriber. Their stored contact details are invalid.");
+    }
+
+    if !deliveries.is_empty() {
+        let attachments: HashMap<Uuid, Vec<Attachment>> = issues
+            .iter()
+            .map(|(issue_id, issue)| {
+                let attachments = issue
+                    .attachments
+                    .iter()
+                    .map(IssueAttachment::as_email_attachment)
+                    .collect();
+                (*issue_id, attachments)
+            })
+            .collect();
+        let messages = deliveries
+            .iter()
+            .map(|delivery| EmailMessage {
+                sender: delivery
+                    .issue
+                    .sender
+                    .as_ref()
+                    .unwrap_or(email_client.sender()),
+                recipient: &delivery.recipient,
+                subject: &delivery.issue.title,
+                html_body: &delivery.issue.html_content,
+                text_body: &delivery.issue.text_content,
+                attachments: &attachments[&delivery.task.newsletter_issue_id],
+            })
+            .collect::<Vec<_>>();
+
+        match email_client.send_email_batch(&messages).await {
+            Ok(results) => {
+                let mut rate_limited = false;
+                for (delivery, result) in deliveries.iter().zip(results) {
+                    match result {
+                        Ok(()) => delete_task(&mut transaction, &delivery.task).await?,
+                        Err(e) => {
+                            rate_limited |= matches!(e, EmailClientError::RateLimited(_));
+                            handle_failed_delivery(&mut transaction, delivery, &e).await?
+                        }
+                    }
+                }
+                if rate_limited {
+                    rate_limiter.back_off(pool).await?;
+                } else {
+                    rate_limiter.recover(pool).await?;
+                }
+            }
+            // The tasks stay in the queue as they are, without using up a retry.
+            Err(EmailClientError::RateLimited(_)) => rate_limiter.back_off(pool).await?,
+            // A failure of the whole batch says nothing about the recipients, so nobody is
+            // suppressed.
+            Err(e) => {
+                match e {
+                    EmailClientError::Unauthorized(_) => alert(&e),
+                    EmailClientError::Rejected(_) => tracing::error!(
+                        alert = true,
+                        error.message = %e,
+                        "The email provider rejected a batch of issues. They will be retried."
+                    ),
+                    _ => tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        "Failed to deliver a batch of issues. They will be retried."
+                    ),
+                }
+                for delivery in &deliveries {
+                    retry_task(&mut transaction, &delivery.task).await?;
+                }
+            }
         }
     }
 
-    delete_task(transaction, issue_id, &email).await?;
+    transaction.commit().await?;
 
     Ok(ExecutionOutcome::TaskCompleted)
 }
 
+/// Retries the task unless the provider will never accept it, in which case the task is dropped
+/// and the recipient suppressed if they are at fault. Rate limited tasks are left as they are,
+/// the workers pause instead.
+async fn handle_failed_delivery(
+    transaction: &mut PgTransaction,
+    delivery: &Delivery,
+    error: &EmailClientError,
+) -> Result<(), Error> {
+    let task = &delivery.task;
+    match error {
+        EmailClientError::Permanent(reason) => {
+            tracing::warn!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                error.message = %error,
+                "Suppressing a subscriber. The email provider will not deliver to their address."
+            );
+            suppressions::add_suppression(
+                transaction,
+                &SuppressedAddress::from(&delivery.recipient),
+                reason,
+                SuppressionSource::Provider,
+                None,
+            )
+            .await?;
+            delete_task(transaction, task).await
+        }
+        EmailClientError::Rejected(_) => {
+            tracing::error!(
+                alert = true,
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                error.message = %error,
+                "The email provider rejected an issue. It will not be retried."
+            );
+            delete_task(transaction, task).await
+        }
+        EmailClientError::Unauthorized(_) => {
+            alert(error);
+            retry_task(transaction, task).await
+        }
+        EmailClientError::RateLimited(_) => Ok(()),
+        EmailClientError::Transient(_) => {
+            tracing::error!(
+                newsletter_issue_id = %task.newsletter_issue_id,
+                subscriber_email = %task.subscriber_email,
+                error.cause_chain = ?error,
+                error.message = %error,
+                "Failed to deliver issue to a confirmed subscriber. It will be retried."
+            );
+            retry_task(transaction, task).await
+        }
+    }
+}
+
+/// Nothing can be delivered until someone fixes the configuration of the email provider.
+fn alert(error: &EmailClientError) {
+    tracing::error!(
+        alert = true,
+        error.message = %error,
+        "The email provider refused to send on our behalf. Check its credentials and sender signatures."
+    );
+}
+
 #[tracing::instrument(skip_all)]
-async fn dequeue_task(pool: &PgPool) -> Result<Option<(PgTransaction, Uuid, String)>, Error> {
+async fn dequeue_tasks(pool: &PgPool) -> Result<Option<(PgTransaction, Vec<Task>)>, Error> {
     let mut transaction = pool.begin().await?;
 
-    let r = sqlx::query!(
-        r#"SELECT newsletter_issue_id, subscriber_email
+    let tasks = sqlx::query_as!(
+        Task,
+        r#"SELECT newsletter_issue_id, subscriber_email, n_retries,
+            EXISTS (
+                SELECT 1 FROM suppressions
+                WHERE address IN (lower(subscriber_email), split_part(lower(subscriber_email), '@', 2))
+            ) AS "suppressed!"
         FROM issue_delivery_queue
+        WHERE execute_after <= now()
         FOR UPDATE
         SKIP LOCKED
-        LIMIT 1"#
+        LIMIT $1"#,
+        BATCH_SIZE
     )
-    .fetch_optional(&mut *transaction)
+    .fetch_all(&mut *transaction)
     .await?;
-    if let Some(r) = r {
-        Ok(Some((
-            transaction,
-            r.newsletter_issue_id,
-            r.subscriber_email,
-        )))
-    } else {
+    if tasks.is_empty() {
         Ok(None)
+    } else {
+        Ok(Some((transaction, tasks)))
     }
 }
 
 #[tracing::instrument(skip_all)]
-async fn delete_task(
-    mut transaction: PgTransaction,
-    issue_id: Uuid,
-    email: &str,
-) -> Result<(), Error> {
+async fn delete_task(transaction: &mut PgTransaction, task: &Task) -> Result<(), Error> {
     let query = sqlx::query!(
         r#"DELETE FROM issue_delivery_queue
         WHERE newsletter_issue_id = $1 AND subscriber_email = $2"#,
-        issue_id,
-        email
+        task.newsletter_issue_id,
+        task.subscriber_email
+    );
+    transaction.execute(query).await?;
+
+    Ok(())
+}
+
+/// Puts a failed task back in the queue, waiting twice as long after every attempt. The task
+/// is dropped once it runs out of retries.
+#[tracing::instrument(skip_all)]
+async fn retry_task(transaction: &mut PgTransaction, task: &Task) -> Result<(), Error> {
+    if task.n_retries >= MAX_RETRIES {
+        tracing::error!(
+            newsletter_issue_id = %task.newsletter_issue_id,
+            subscriber_email = %task.subscriber_email,
+            "Giving up on delivering an issue after {} retries.",
+            MAX_RETRIES
+        );
+        return delete_task(transaction, task).await;
+    }
+
+    let delay = chrono::Duration::seconds(RETRY_DELAY_SECONDS << task.n_retries);
+    let query = sqlx::query!(
+        r#"UPDATE issue_delivery_queue
+        SET n_retries = n_retries + 1, execute_after = $3
+        WHERE newsletter_issue_id = $1 AND subscriber_email = $2"#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        Utc::now() + delay
     );
     transaction.execute(query).await?;
-    transaction.commit().await?;
 
     Ok(())
 }
 
 #[tracing::instrument(skip_all)]
-async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, Error> {
-    let issue = sqlx::query_as!(
-        NewsletterIssue,
-        r#"SELECT title, text_content, html_content
-        FROM newsletter_issues
-        WHERE newsletter_issue_id = $1"#,
+async fn get_issue(
+    pool: &PgPool,
+    issue_id: Uuid,
+    base_url: &str,
+) -> Result<NewsletterIssue, Error> {
+    let issue = sqlx::query!(
+        r#"SELECT i.newsletter_id, n.sender_email, i.title, i.text_content, i.html_content,
+            i.tracking_enabled
+        FROM newsletter_issues i
+        JOIN newsletters n ON n.newsletter_id = i.newsletter_id
+        WHERE i.newsletter_issue_id = $1"#,
         issue_id
     )
     .fetch_one(pool)
     .await?;
 
-    Ok(issue)
+    let tracked_links = if issue.tracking_enabled {
+        let links = tracking::trackable_links(&issue.html_content, base_url);
+        tracking::store_links(issue_id, &links, pool).await?;
+        links
+    } else {
+        Vec::new()
+    };
+    let attachments = attachments::get_issue_attachments(issue_id, pool).await?;
+
+    Ok(NewsletterIssue {
+        newsletter_id: issue.newsletter_id,
+        sender_email: issue.sender_email,
+        title: issue.title,
+        text_content: issue.text_content,
+        html_content: issue.html_content,
+        tracking_enabled: issue.tracking_enabled,
+        tracked_links,
+        attachments,
+    })
+}
+
+#[tracing::instrument(skip_all)]
+/// The subscribers of a newsletter with the given addresses, by address.
+async fn get_subscribers(
+    pool: &PgPool,
+    newsletter_id: Uuid,
+    emails: &[String],
+) -> Result<HashMap<String, Subscriber>, Error> {
+    let subscribers = sqlx::query_as!(
+        Subscriber,
+        r#"SELECT id, email, name, unsubscribe_token
+        FROM subscriptions
+        WHERE newsletter_id = $1 AND email = ANY($2)"#,
+        newsletter_id,
+        emails
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(subscribers
+        .into_iter()
+        .map(|s| (s.email.clone(), s))
+        .collect())
 }
 
-async fn worker_loop(pool: PgPool, email_client: EmailClient) -> Result<(), Error> {
+/// The addresses of the tasks, grouped by the newsletter of their issue.
+fn group_by_newsletter(
+    tasks: &[Task],
+    issues: &HashMap<Uuid, NewsletterIssue>,
+) -> HashMap<Uuid, Vec<String>> {
+    let mut emails: HashMap<Uuid, Vec<String>> = HashMap::new();
+    for task in tasks {
+        emails
+            .entry(issues[&task.newsletter_issue_id].newsletter_id)
+            .or_default()
+            .push(task.subscriber_email.clone());
+    }
+
+    emails
+}
+
+fn personalise(
+    issue: &NewsletterIssue,
+    subscriber: Option<&Subscriber>,
+    email: &SubscriberEmail,
+    base_url: &str,
+) -> Result<PersonalisedIssue, String> {
+    let subscriber = subscriber.ok_or("The subscriber no longer exists.")?;
+    let sender = issue
+        .sender_email
+        .clone()
+        .map(SubscriberEmail::parse)
+        .transpose()?;
+    let unsubscribe_url = format!(
+        "{}/subscriptions/unsubscribe?unsubscribe_token={}",
+        base_url, subscriber.unsubscribe_token
+    );
+    let preferences_url = format!(
+        "{}/preferences?token={}",
+        base_url, subscriber.unsubscribe_token
+    );
+    let values = MergeValues {
+        name: &subscriber.name,
+        email: email.as_ref(),
+        unsubscribe_url: &unsubscribe_url,
+        preferences_url: &preferences_url,
+    };
+
+    Ok(PersonalisedIssue {
+        sender,
+        title: merge_fields::render_text(&issue.title, &values)?,
+        text_content: merge_fields::render_text(&issue.text_content, &values)?,
+        html_content: merge_fields::render_html(&issue.html_content, &values)?,
+    })
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: Arc<dyn EmailTransport>,
+    rate_limiter: SendRateLimiter,
+    base_url: String,
+) -> Result<(), Error> {
     loop {
-        match try_execute_task(&pool, &email_client).await {
+        match try_execute_task(&pool, email_client.as_ref(), &rate_limiter, &base_url).await {
             Ok(ExecutionOutcome::EmptyQueue) => {
                 time::sleep(Duration::from_secs(10)).await;
             }
+            Ok(ExecutionOutcome::Throttled(delay)) => {
+                time::sleep(delay).await;
+            }
             Ok(ExecutionOutcome::TaskCompleted) => {}
             Err(_) => {
                 time::sleep(Duration::from_secs(1)).await;
@@ -1,11 +1,23 @@
+pub mod attachments;
+pub mod audit;
 pub mod authentication;
 pub mod configuration;
 pub mod domain;
 pub mod email_client;
+pub mod email_events;
+pub mod html;
 pub mod idempotency;
 pub mod issue_delivery_worker;
+pub mod markdown;
+pub mod merge_fields;
+pub mod newsletters;
 pub mod routes;
+pub mod security_headers;
+pub mod send_rate;
 pub mod session_state;
 pub mod startup;
+pub mod subscribers;
+pub mod suppressions;
 pub mod telemetry;
+pub mod tracking;
 pub mod utils;
@@ -0,0 +1,155 @@
+use pulldown_cmark::{html, Event, Options, Parser, Tag, TagEnd};
+
+/// The two bodies of a newsletter issue, generated from a single Markdown source.
+#[derive(Debug)]
+pub struct RenderedMarkdown {
+    pub html: String,
+    pub text: String,
+}
+
+pub fn render(markdown: &str) -> RenderedMarkdown {
+    RenderedMarkdown {
+        html: render_html(markdown),
+        text: render_text(markdown),
+    }
+}
+
+fn parser(markdown: &str) -> Parser<'_> {
+    Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    )
+}
+
+/// Raw HTML is allowed in Markdown, so the output goes through the sanitiser before use.
+fn render_html(markdown: &str) -> String {
+    let mut unsafe_html = String::new();
+    html::push_html(&mut unsafe_html, parser(markdown));
+    // Link destinations are percent-encoded, which would hide merge fields such as
+    // `[Unsubscribe]({{unsubscribe_url}})` from the delivery worker.
+    let unsafe_html = unsafe_html.replace("%7B%7B", "{{").replace("%7D%7D", "}}");
+
+    crate::html::sanitiser().clean(&unsafe_html).to_string()
+}
+
+fn render_text(markdown: &str) -> String {
+    let mut text = String::new();
+    // The next item number of each open list, `None` for bullet lists.
+    let mut lists: Vec<Option<u64>> = Vec::new();
+    let mut link_urls: Vec<String> = Vec::new();
+
+    for event in parser(markdown) {
+        match event {
+            Event::Start(Tag::List(first_number)) => {
+                if !lists.is_empty() {
+                    text.push('\n');
+                }
+                lists.push(first_number);
+            }
+            Event::End(TagEnd::List(_)) => {
+                lists.pop();
+                if lists.is_empty() {
+                    text.push('\n');
+                }
+            }
+            Event::Start(Tag::Item) => {
+                text.push_str(&"  ".repeat(lists.len().saturating_sub(1)));
+                match lists.last_mut() {
+                    Some(Some(number)) => {
+                        text.push_str(&format!("{}. ", number));
+                        *number += 1;
+                    }
+                    _ => text.push_str("- "),
+                }
+            }
+            Event::End(TagEnd::Item) if !text.ends_with('\n') => text.push('\n'),
+            Event::End(TagEnd::Paragraph) => {
+                text.push_str(if lists.is_empty() { "\n\n" } else { "\n" });
+            }
+            Event::End(TagEnd::Heading(_)) | Event::End(TagEnd::BlockQuote(_)) => {
+                text.push_str("\n\n");
+            }
+            Event::End(TagEnd::CodeBlock) => text.push('\n'),
+            Event::Start(Tag::Link { dest_url, .. })
+            | Event::Start(Tag::Image { dest_url, .. }) => {
+                link_urls.push(dest_url.into_string());
+            }
+            Event::End(TagEnd::Link) | Event::End(TagEnd::Image) => {
+                if let Some(url) = link_urls.pop() {
+                    if !text.ends_with(url.as_str()) {
+                        text.push_str(&format!(" ({})", url));
+                    }
+                }
+            }
+            Event::End(TagEnd::TableRow) | Event::End(TagEnd::TableHead) => text.push('\n'),
+            Event::End(TagEnd::TableCell) => text.push('\t'),
+            Event::Text(s) | Event::Code(s) => text.push_str(&s),
+            Event::SoftBreak | Event::HardBreak => text.push('\n'),
+            Event::Rule => text.push_str("----\n\n"),
+            _ => {}
+        }
+    }
+
+    text.trim_end().to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let rendered = render("# Title\n\nSome *emphasis* and a [link](https://example.com).");
+
+        assert!(rendered.html.contains("<h1>Title</h1>"));
+        assert!(rendered.html.contains("<em>emphasis</em>"));
+        assert!(rendered.html.contains(r#"href="https://example.com""#));
+    }
+
+    #[test]
+    fn merge_fields_are_kept_in_links() {
+        let rendered = render("[Unsubscribe]({{unsubscribe_url}})");
+
+        assert!(rendered.html.contains(r#"href="{{unsubscribe_url}}""#));
+        assert_eq!("Unsubscribe ({{unsubscribe_url}})", rendered.text);
+    }
+
+    #[test]
+    fn raw_html_is_sanitised() {
+        let rendered = render("Hello <script>alert('xss')</script><b onclick=\"x()\">world</b>");
+
+        assert!(!rendered.html.contains("<script>"));
+        assert!(!rendered.html.contains("onclick"));
+        assert!(rendered.html.contains("<b>world</b>"));
+    }
+
+    #[test]
+    fn plain_text_keeps_the_structure_readable() {
+        let rendered = render(
+            "# Title\n\nFirst paragraph with a [link](https://example.com).\n\n\
+             - one\n- two\n\n1. first\n2. second",
+        );
+
+        assert_eq!(
+            "Title\n\n\
+             First paragraph with a link (https://example.com).\n\n\
+             - one\n- two\n\n\
+             1. first\n2. second",
+            rendered.text
+        );
+    }
+
+    #[test]
+    fn plain_text_does_not_repeat_bare_urls() {
+        let rendered = render("<https://example.com>");
+
+        assert_eq!("https://example.com", rendered.text);
+    }
+
+    #[test]
+    fn plain_text_drops_raw_html() {
+        let rendered = render("Hello <b>world</b>");
+
+        assert_eq!("Hello world", rendered.text);
+    }
+}
@@ -0,0 +1,157 @@
+//! Per-subscriber placeholders such as `{{name}}` in the title and bodies of newsletter issues.
+//! Issues are checked for unknown placeholders when they are published, and rendered for each
+//! recipient when they are delivered.
+
+use crate::html;
+
+const FIELDS: &[&str] = &["name", "email", "unsubscribe_url", "preferences_url"];
+
+/// The values of the merge fields for a single recipient.
+#[derive(Debug)]
+pub struct MergeValues<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    pub unsubscribe_url: &'a str,
+    pub preferences_url: &'a str,
+}
+
+impl MergeValues<'_> {
+    fn get(&self, field: &str) -> Option<&str> {
+        match field {
+            "name" => Some(self.name),
+            "email" => Some(self.email),
+            "unsubscribe_url" => Some(self.unsubscribe_url),
+            "preferences_url" => Some(self.preferences_url),
+            _ => None,
+        }
+    }
+}
+
+enum Segment<'a> {
+    Literal(&'a str),
+    Field(&'a str),
+}
+
+/// Checks that every placeholder in the template is a known merge field.
+pub fn validate(template: &str) -> Result<(), String> {
+    for segment in segments(template)? {
+        if let Segment::Field(field) = segment {
+            if !FIELDS.contains(&field) {
+                let available = FIELDS
+                    .iter()
+                    .map(|f| format!("{{{{{}}}}}", f))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                return Err(format!(
+                    "{{{{{}}}}} is not a known merge field. Use one of {}.",
+                    field, available
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Renders a plain text template, such as the subject or the text body.
+pub fn render_text(template: &str, values: &MergeValues) -> Result<String, String> {
+    render(template, values, |value, output| output.push_str(value))
+}
+
+/// Renders an HTML template. Values are escaped, so they are safe in both text and attributes.
+pub fn render_html(template: &str, values: &MergeValues) -> Result<String, String> {
+    render(template, values, |value, output| {
+        output.push_str(&html::escape(value))
+    })
+}
+
+fn render(
+    template: &str,
+    values: &MergeValues,
+    push_value: impl Fn(&str, &mut String),
+) -> Result<String, String> {
+    let mut output = String::with_capacity(template.len());
+    for segment in segments(template)? {
+        match segment {
+            Segment::Literal(text) => output.push_str(text),
+            Segment::Field(field) => {
+                let value = values
+                    .get(field)
+                    .ok_or_else(|| format!("{{{{{}}}}} is not a known merge field.", field))?;
+                push_value(value, &mut output);
+            }
+        }
+    }
+
+    Ok(output)
+}
+
+fn segments(template: &str) -> Result<Vec<Segment<'_>>, String> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        segments.push(Segment::Literal(&rest[..start]));
+        let field_and_rest = &rest[start + 2..];
+        let end = field_and_rest
+            .find("}}")
+            .ok_or("A merge field is missing its closing braces.")?;
+        segments.push(Segment::Field(field_and_rest[..end].trim()));
+        rest = &field_and_rest[end + 2..];
+    }
+    segments.push(Segment::Literal(rest));
+
+    Ok(segments)
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok, assert_ok_eq};
+
+    use super::{render_html, render_text, validate, MergeValues};
+
+    const VALUES: MergeValues = MergeValues {
+        name: "Ursula <Le Guin>",
+        email: "ursula@example.com",
+        unsubscribe_url: "https://example.com/unsubscribe?token=a&b",
+        preferences_url: "https://example.com/preferences?token=a",
+    };
+
+    #[test]
+    fn known_merge_fields_are_valid() {
+        assert_ok!(validate(
+            "Hi {{name}}, {{ email }}: {{unsubscribe_url}} {{preferences_url}}"
+        ));
+        assert_ok!(validate("No merge fields at all"));
+    }
+
+    #[test]
+    fn unknown_merge_fields_are_rejected() {
+        assert_err!(validate("Hi {{first_name}}"));
+    }
+
+    #[test]
+    fn unterminated_merge_fields_are_rejected() {
+        assert_err!(validate("Hi {{name"));
+    }
+
+    #[test]
+    fn values_are_inserted_verbatim_in_text() {
+        assert_ok_eq!(
+            render_text("Hi {{name}}!", &VALUES),
+            "Hi Ursula <Le Guin>!".to_string()
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        assert_ok_eq!(
+            render_html(
+                r#"<p>Hi {{ name }}</p><a href="{{unsubscribe_url}}">Unsubscribe</a>"#,
+                &VALUES
+            ),
+            "<p>Hi Ursula &lt;Le Guin&gt;</p>\
+             <a href=\"https://example.com/unsubscribe?token=a&amp;b\">Unsubscribe</a>"
+                .to_string()
+        );
+    }
+}
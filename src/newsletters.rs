@@ -0,0 +1,107 @@
+use anyhow::{Context, Error};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use crate::domain::{NewsletterSlug, SubscriberEmail};
+
+/// The newsletter that existing subscribers and issues were moved to. It also serves
+/// `POST /subscriptions`.
+pub const DEFAULT_NEWSLETTER: &str = "default";
+
+#[derive(Debug)]
+pub struct Newsletter {
+    pub newsletter_id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub sender_email: Option<String>,
+}
+
+impl Newsletter {
+    /// The address emails of this newsletter are sent from, if it does not use the configured
+    /// sender.
+    pub fn sender(&self) -> Result<Option<SubscriberEmail>, String> {
+        self.sender_email
+            .clone()
+            .map(SubscriberEmail::parse)
+            .transpose()
+    }
+}
+
+pub struct NewsletterSummary {
+    pub slug: String,
+    pub name: String,
+    pub sender_email: Option<String>,
+    pub confirmed_subscribers: i64,
+    pub issues: i64,
+}
+
+#[tracing::instrument(name = "Get newsletter", skip(executor))]
+pub async fn get_newsletter(
+    slug: &str,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<Newsletter>, Error> {
+    let newsletter = sqlx::query_as!(
+        Newsletter,
+        r#"SELECT newsletter_id, slug, name, sender_email
+        FROM newsletters
+        WHERE slug = $1"#,
+        slug
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to retrieve a newsletter.")?;
+
+    Ok(newsletter)
+}
+
+/// Every newsletter, oldest first, so that the default newsletter comes first.
+#[tracing::instrument(name = "Get newsletters", skip(pool))]
+pub async fn get_newsletters(pool: &PgPool) -> Result<Vec<NewsletterSummary>, Error> {
+    let newsletters = sqlx::query_as!(
+        NewsletterSummary,
+        r#"SELECT
+            n.slug,
+            n.name,
+            n.sender_email,
+            (
+                SELECT COUNT(*) FROM subscriptions s
+                WHERE s.newsletter_id = n.newsletter_id AND s.status = 'confirmed'
+            ) AS "confirmed_subscribers!",
+            (
+                SELECT COUNT(*) FROM newsletter_issues i
+                WHERE i.newsletter_id = n.newsletter_id
+            ) AS "issues!"
+        FROM newsletters n
+        ORDER BY n.created_at, n.slug"#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the newsletters.")?;
+
+    Ok(newsletters)
+}
+
+/// Returns `None` if the slug is already taken.
+#[tracing::instrument(name = "Create newsletter", skip(executor))]
+pub async fn create_newsletter(
+    slug: &NewsletterSlug,
+    name: &str,
+    sender_email: Option<&SubscriberEmail>,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<Uuid>, Error> {
+    let row = sqlx::query!(
+        r#"INSERT INTO newsletters (newsletter_id, slug, name, sender_email, created_at)
+        VALUES ($1, $2, $3, $4, now())
+        ON CONFLICT (slug) DO NOTHING
+        RETURNING newsletter_id"#,
+        Uuid::new_v4(),
+        slug.as_ref(),
+        name,
+        sender_email.map(|e| e.as_ref())
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to create a newsletter.")?;
+
+    Ok(row.map(|r| r.newsletter_id))
+}
@@ -0,0 +1,38 @@
+use actix_web::{web::Data, Error, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    attachments::{self, AttachmentSummary},
+    authentication, html,
+    session_state::TypedSession,
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/attachments.html")]
+struct AttachmentsPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    attachments: Vec<AttachmentSummary>,
+    max_size_mb: usize,
+    csrf_token: &'a str,
+}
+
+pub async fn attachment_list(
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+    let attachments = attachments::get_pending_attachments(&pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&AttachmentsPage {
+        flash_messages: &flash_messages,
+        attachments,
+        max_size_mb: attachments::MAX_ATTACHMENT_SIZE / 1024 / 1024,
+        csrf_token: &csrf_token,
+    })
+}
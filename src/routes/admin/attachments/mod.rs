@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::attachment_list;
+pub use post::{delete_attachment, upload_attachment};
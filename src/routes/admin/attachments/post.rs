@@ -0,0 +1,146 @@
+use actix_multipart::form::{bytes::Bytes, text::Text, MultipartForm};
+use actix_web::{
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    attachments,
+    audit::{self, AuditAction},
+    authentication::UserId,
+    domain::AttachmentFile,
+    utils,
+};
+
+#[derive(MultipartForm)]
+pub struct UploadFormData {
+    file: Bytes,
+    inline: Option<Text<bool>>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeleteFormData {
+    attachment_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Upload an attachment",
+    skip_all,
+    fields(user_id=%&*user_id, file_name=tracing::field::Empty)
+)]
+pub async fn upload_attachment(
+    form: MultipartForm<UploadFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let UploadFormData { file, inline } = form.into_inner();
+    let inline = inline.is_some_and(|inline| inline.into_inner());
+
+    let Some(name) = file.file_name.filter(|name| !name.is_empty()) else {
+        FlashMessage::error("Choose a file to upload.").send();
+        return Ok(utils::see_other("/admin/attachments"));
+    };
+    tracing::Span::current().record("file_name", tracing::field::display(&name));
+    if file.data.is_empty() {
+        FlashMessage::error(format!("{} is empty.", name)).send();
+        return Ok(utils::see_other("/admin/attachments"));
+    }
+    if file.data.len() > attachments::MAX_ATTACHMENT_SIZE {
+        FlashMessage::error(format!(
+            "{} is too large. Files cannot be larger than {} MB.",
+            name,
+            attachments::MAX_ATTACHMENT_SIZE / 1024 / 1024
+        ))
+        .send();
+        return Ok(utils::see_other("/admin/attachments"));
+    }
+    let content_type = file
+        .content_type
+        .map(|content_type| content_type.to_string())
+        .unwrap_or_default();
+    let attachment_file = match AttachmentFile::parse(name, content_type) {
+        Ok(attachment_file) => attachment_file,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/attachments"));
+        }
+    };
+    if inline && !attachment_file.is_image() {
+        FlashMessage::error("Only images can be shown inline.").send();
+        return Ok(utils::see_other("/admin/attachments"));
+    }
+    if let Err(e) = attachment_file.check_content(&file.data) {
+        FlashMessage::error(e).send();
+        return Ok(utils::see_other("/admin/attachments"));
+    }
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    attachments::store_attachment(
+        &attachment_file,
+        &file.data,
+        inline,
+        **user_id,
+        &mut *transaction,
+    )
+    .await
+    .map_err(utils::e500)?;
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::UploadAttachment,
+        Some(attachment_file.name()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!(
+        "{} has been uploaded. Pick it when publishing an issue.",
+        attachment_file.name()
+    ))
+    .send();
+
+    Ok(utils::see_other("/admin/attachments"))
+}
+
+#[tracing::instrument(
+    name = "Delete an attachment",
+    skip_all,
+    fields(user_id=%&*user_id, attachment_id=%form.attachment_id)
+)]
+pub async fn delete_attachment(
+    form: Form<DeleteFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let Some(file_name) =
+        attachments::delete_pending_attachment(form.attachment_id, &mut *transaction)
+            .await
+            .map_err(utils::e500)?
+    else {
+        FlashMessage::error("The attachment does not exist or has already been sent.").send();
+        return Ok(utils::see_other("/admin/attachments"));
+    };
+
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::DeleteAttachment,
+        Some(&file_name),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!("{} has been deleted.", file_name)).send();
+
+    Ok(utils::see_other("/admin/attachments"))
+}
@@ -0,0 +1,60 @@
+use actix_web::{
+    web::{Data, Query},
+    Error, HttpResponse,
+};
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    audit::{self, AuditAction, AuditLogEntry, AuditLogFilter},
+    authentication, html,
+    session_state::TypedSession,
+    utils,
+};
+
+const MAX_ENTRIES: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    action: Option<String>,
+    actor: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/audit.html")]
+struct AuditLogPage<'a> {
+    entries: Vec<AuditLogEntry>,
+    actions: &'static [AuditAction],
+    selected_action: &'a str,
+    actor: &'a str,
+    csrf_token: &'a str,
+}
+
+pub async fn audit_log(
+    query: Query<QueryParams>,
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let action = utils::non_empty(&query.action);
+    let actor = utils::non_empty(&query.actor);
+
+    let filter = AuditLogFilter {
+        action: action
+            .map(AuditAction::parse)
+            .transpose()
+            .map_err(utils::e400)?,
+        actor: actor.map(str::to_owned),
+    };
+    let entries = audit::get_audit_log(&filter, MAX_ENTRIES, &pool)
+        .await
+        .map_err(utils::e500)?;
+    let csrf_token = authentication::csrf_token(&session)?;
+
+    html::render(&AuditLogPage {
+        entries,
+        actions: AuditAction::ALL,
+        selected_action: action.unwrap_or_default(),
+        actor: actor.unwrap_or_default(),
+        csrf_token: &csrf_token,
+    })
+}
@@ -1,9 +1,17 @@
-use actix_web::{http::header::ContentType, web::Data, HttpResponse};
+use actix_web::{web::Data, HttpResponse};
 use anyhow::Context;
+use askama::Template;
 use sqlx::PgPool;
 use uuid::Uuid;
 
-use crate::{session_state::TypedSession, utils};
+use crate::{authentication, html, session_state::TypedSession, utils};
+
+#[derive(Template)]
+#[template(path = "admin/dashboard.html")]
+struct DashboardPage<'a> {
+    username: &'a str,
+    csrf_token: &'a str,
+}
 
 pub async fn admin_dashboard(
     session: TypedSession,
@@ -11,30 +19,12 @@ pub async fn admin_dashboard(
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = session.get_user_id().map_err(utils::e500)?.unwrap();
     let username = get_username(user_id, &pool).await.map_err(utils::e500)?;
+    let csrf_token = authentication::csrf_token(&session)?;
 
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(format!(
-            r#"<!DOCTYPE html>
-<html lang="en">
-    <head>
-        <meta http-equiv="content-type" content="text/html; charset=utf-8">
-        <title>Admin dashboard</title>
-    </head>
-    <body>
-        <p>Welcome {username}!</p>
-        <p>Available actions:</p>
-        <ol>
-            <li><a href="/admin/password">Change password</a></li>
-            <li>
-                <form name="logoutForm" action="/admin/logout" method="post">
-                    <input type="submit" value="Logout">
-                </form>
-            </li>
-        </ol>
-    </body>
-</html>"#
-        )))
+    html::render(&DashboardPage {
+        username: &username,
+        csrf_token: &csrf_token,
+    })
 }
 
 #[tracing::instrument(name = "Get username", skip(pool))]
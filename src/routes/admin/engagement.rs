@@ -0,0 +1,32 @@
+use actix_web::{web::Data, Error, HttpResponse};
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    authentication, html,
+    session_state::TypedSession,
+    tracking::{self, EngagementReport},
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/engagement.html")]
+struct EngagementPage<'a> {
+    issues: Vec<EngagementReport>,
+    csrf_token: &'a str,
+}
+
+pub async fn engagement_report(
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+    let issues = tracking::get_engagement_report(&pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&EngagementPage {
+        issues,
+        csrf_token: &csrf_token,
+    })
+}
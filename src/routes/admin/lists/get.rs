@@ -0,0 +1,36 @@
+use actix_web::{web::Data, Error, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    authentication, html,
+    newsletters::{self, NewsletterSummary},
+    session_state::TypedSession,
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/lists.html")]
+struct NewsletterListsPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    newsletters: Vec<NewsletterSummary>,
+    csrf_token: &'a str,
+}
+
+pub async fn newsletter_lists(
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+    let newsletters = newsletters::get_newsletters(&pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&NewsletterListsPage {
+        flash_messages: &flash_messages,
+        newsletters,
+        csrf_token: &csrf_token,
+    })
+}
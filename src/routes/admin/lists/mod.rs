@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::newsletter_lists;
+pub use post::create_newsletter;
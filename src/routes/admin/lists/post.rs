@@ -0,0 +1,96 @@
+use actix_web::{
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{
+    audit::{self, AuditAction},
+    authentication::UserId,
+    domain::{NewsletterSlug, SubscriberEmail},
+    newsletters, utils,
+};
+
+#[derive(serde::Deserialize)]
+pub struct CreateFormData {
+    slug: String,
+    name: String,
+    #[serde(default)]
+    sender_email: String,
+}
+
+#[tracing::instrument(
+    name = "Create a newsletter",
+    skip_all,
+    fields(user_id=%&*user_id, slug=%form.slug)
+)]
+pub async fn create_newsletter(
+    form: Form<CreateFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let CreateFormData {
+        slug,
+        name,
+        sender_email,
+    } = form.0;
+
+    let name = name.trim();
+    if name.is_empty() || name.graphemes(true).count() > 100 {
+        FlashMessage::error("The newsletter name must be between 1 and 100 characters long.")
+            .send();
+        return Ok(utils::see_other("/admin/lists"));
+    }
+    let slug = match NewsletterSlug::parse(slug.trim().to_owned()) {
+        Ok(slug) => slug,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/lists"));
+        }
+    };
+    let sender_email = match sender_email.trim() {
+        "" => None,
+        email => match SubscriberEmail::parse(email.to_owned()) {
+            Ok(email) => Some(email),
+            Err(_) => {
+                FlashMessage::error(format!("{} is not a valid sender address.", email)).send();
+                return Ok(utils::see_other("/admin/lists"));
+            }
+        },
+    };
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let Some(newsletter_id) =
+        newsletters::create_newsletter(&slug, name, sender_email.as_ref(), &mut *transaction)
+            .await
+            .map_err(utils::e500)?
+    else {
+        FlashMessage::error(format!(
+            "There is already a newsletter called {}.",
+            slug.as_ref()
+        ))
+        .send();
+        return Ok(utils::see_other("/admin/lists"));
+    };
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::CreateNewsletter,
+        Some(&newsletter_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!(
+        "The newsletter has been created. Readers can subscribe at /newsletters/{}/subscriptions.",
+        slug.as_ref()
+    ))
+    .send();
+
+    Ok(utils::see_other("/admin/lists"))
+}
@@ -1,9 +1,43 @@
-use actix_web::{Error, HttpResponse};
+use actix_web::{
+    web::{Data, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
 use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
 
-use crate::{session_state::TypedSession, utils};
+use crate::{
+    audit::{self, AuditAction},
+    authentication::{self, UserId},
+    session_state::TypedSession,
+    utils,
+};
+
+pub async fn log_out(
+    request: HttpRequest,
+    session: TypedSession,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+
+    let session_id = session.get_session_id().map_err(utils::e500)?;
+    if let Some(session_id) = session_id {
+        authentication::revoke_session(session_id, **user_id, &mut *transaction)
+            .await
+            .map_err(utils::e500)?;
+    }
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::LogOut,
+        session_id.map(|id| id.to_string()).as_deref(),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    transaction.commit().await.map_err(utils::e500)?;
 
-pub async fn log_out(session: TypedSession) -> Result<HttpResponse, Error> {
     session.log_out();
     FlashMessage::info("You have successfully logged out.").send();
     Ok(utils::see_other("/login"))
@@ -1,9 +1,27 @@
+mod attachments;
+mod audit;
 mod dashboard;
+mod engagement;
+mod lists;
 mod logout;
 mod newsletter;
 mod password;
+mod sessions;
+mod subscribers;
+mod suppressions;
+mod tokens;
+mod webhooks;
 
+pub use attachments::*;
+pub use audit::audit_log;
 pub use dashboard::admin_dashboard;
+pub use engagement::engagement_report;
+pub use lists::*;
 pub use logout::log_out;
 pub use newsletter::*;
 pub use password::*;
+pub use sessions::*;
+pub use subscribers::*;
+pub use suppressions::*;
+pub use tokens::*;
+pub use webhooks::postmark_webhook;
@@ -1,50 +1,120 @@
-use actix_web::{http::header::ContentType, Error, HttpResponse};
+use actix_web::{
+    web::{Data, Query},
+    Error, HttpResponse,
+};
 use actix_web_flash_messages::IncomingFlashMessages;
-use std::fmt::Write;
+use anyhow::Context;
+use askama::Template;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::{
+    attachments::{self, AttachmentSummary},
+    authentication, html,
+    newsletters::{self, Newsletter, NewsletterSummary},
+    session_state::TypedSession,
+    subscribers::{self, TagSummary},
+    utils,
+};
+
+const MAX_RECENT_ISSUES: i64 = 20;
+
+struct IssueSummary {
+    title: String,
+    published_at: String,
+    author: Option<String>,
+    last_edited_by: Option<String>,
+    last_edited_at: Option<DateTime<Utc>>,
+    segments: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    newsletter: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/publish_newsletter.html")]
+struct PublishNewsletterPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    newsletter: Newsletter,
+    newsletters: Vec<NewsletterSummary>,
+    recent_issues: Vec<IssueSummary>,
+    tags: Vec<TagSummary>,
+    attachments: Vec<AttachmentSummary>,
+    idempotency_key: Uuid,
+    csrf_token: &'a str,
+}
+
 pub async fn publish_newsletter_form(
+    query: Query<QueryParams>,
     flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    session: TypedSession,
 ) -> Result<HttpResponse, Error> {
-    let mut msg_html = String::new();
-    for msg in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", msg.content()).unwrap();
-    }
-
-    let idempotency_key = Uuid::new_v4();
-
-    Ok(HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(format!(
-            r#"<!DOCTYPE html>
-<html lang="en">
-    <head>
-        <meta http-equiv="content-type" content="text/html; charset=utf-8">
-        <title>Publish newsletter issue</title>
-    </head>
-    <body>
-        {msg_html}
-        <form action="/admin/newsletters" method="post">
-            <label>
-                Title:
-                <br>
-                <input type="text" placeholder="Enter the issue title" name="title">
-            </label>
-            <br>
-            <label>
-                Plain text content:<br>
-                <textarea placeholder="Enter the content in plain text" name="text_content" rows="20" cols="50"></textarea>
-            </label>
-            <br>
-            <label>HTML content:<br>
-                <textarea placeholder="Enter the content in HTML format" name="html_content" rows="20" cols="50"></textarea>
-            </label>
-            <br>
-            <input hidden type="text" name="idempotency_key" value="{idempotency_key}">
-            <button type="submit">Publish</button>
-        </form>
-        <p><a href="/admin/dashboard">&lt;- Back</a></p>
-    </body>
-</html>"#,
-        )))
+    let slug = query
+        .newsletter
+        .as_deref()
+        .filter(|n| !n.is_empty())
+        .unwrap_or(newsletters::DEFAULT_NEWSLETTER);
+    let newsletter = newsletters::get_newsletter(slug, pool.get_ref())
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| utils::e404(format!("There is no newsletter called {}.", slug)))?;
+
+    let csrf_token = authentication::csrf_token(&session)?;
+    let newsletters = newsletters::get_newsletters(&pool)
+        .await
+        .map_err(utils::e500)?;
+    let recent_issues = get_recent_issues(&newsletter, &pool)
+        .await
+        .map_err(utils::e500)?;
+    let tags = subscribers::get_tags(newsletter.newsletter_id, &pool)
+        .await
+        .map_err(utils::e500)?;
+    let attachments = attachments::get_pending_attachments(&pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&PublishNewsletterPage {
+        flash_messages: &flash_messages,
+        newsletter,
+        newsletters,
+        recent_issues,
+        tags,
+        attachments,
+        idempotency_key: Uuid::new_v4(),
+        csrf_token: &csrf_token,
+    })
+}
+
+#[tracing::instrument(name = "Get recent newsletter issues", skip(pool))]
+async fn get_recent_issues(
+    newsletter: &Newsletter,
+    pool: &PgPool,
+) -> Result<Vec<IssueSummary>, anyhow::Error> {
+    let issues = sqlx::query_as!(
+        IssueSummary,
+        r#"SELECT
+            i.title,
+            i.published_at,
+            author.username AS "author?",
+            editor.username AS "last_edited_by?",
+            i.last_edited_at,
+            i.segments
+        FROM newsletter_issues i
+        LEFT JOIN users author ON author.user_id = i.author_id
+        LEFT JOIN users editor ON editor.user_id = i.last_edited_by
+        WHERE i.newsletter_id = $1
+        ORDER BY i.published_at DESC
+        LIMIT $2"#,
+        newsletter.newsletter_id,
+        MAX_RECENT_ISSUES
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the recent newsletter issues.")?;
+
+    Ok(issues)
 }
@@ -1,23 +1,41 @@
 use actix_web::{
-    web::{Data, Form, ReqData},
-    HttpResponse,
+    web::{Data, ReqData},
+    HttpRequest, HttpResponse,
 };
 use actix_web_flash_messages::FlashMessage;
+use actix_web_lab::extract::UrlEncodedForm;
 use anyhow::Context;
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::{
+    attachments,
+    audit::{self, AuditAction},
     authentication::UserId,
+    domain::{NewsletterBody, NewsletterTitle, SubscriberTag},
     idempotency::{self, IdempotencyKey, NextAction},
+    markdown,
+    newsletters::{self, Newsletter},
     utils,
 };
 
 #[derive(serde::Deserialize)]
 pub struct FormData {
     title: String,
+    #[serde(default)]
+    markdown_content: String,
+    #[serde(default)]
     text_content: String,
+    #[serde(default)]
     html_content: String,
+    #[serde(default)]
+    segments: Vec<String>,
+    #[serde(default)]
+    newsletter: String,
+    #[serde(default)]
+    track_engagement: bool,
+    #[serde(default)]
+    attachments: Vec<String>,
     idempotency_key: String,
 }
 
@@ -27,19 +45,60 @@ pub struct FormData {
     fields(user_id=%&*user_id)
 )]
 pub async fn publish_newsletter(
-    form: Form<FormData>,
+    form: UrlEncodedForm<FormData>,
+    request: HttpRequest,
     pool: Data<PgPool>,
     user_id: ReqData<UserId>,
 ) -> Result<HttpResponse, actix_web::Error> {
     let user_id = user_id.into_inner();
     let FormData {
         title,
+        markdown_content,
         text_content,
         html_content,
+        segments,
+        newsletter,
+        track_engagement,
+        attachments,
         idempotency_key,
     } = form.0;
     let idempotency_key: IdempotencyKey = idempotency_key.try_into().map_err(utils::e400)?;
 
+    let slug = if newsletter.is_empty() {
+        newsletters::DEFAULT_NEWSLETTER
+    } else {
+        &newsletter
+    };
+    let Some(newsletter) = newsletters::get_newsletter(slug, pool.get_ref())
+        .await
+        .map_err(utils::e500)?
+    else {
+        FlashMessage::error(format!("There is no newsletter called {}.", slug)).send();
+        return Ok(utils::see_other("/admin/newsletters"));
+    };
+    let form_url = publish_form_url(&newsletter);
+
+    let (title, body, segments) = match parse_issue(
+        title,
+        markdown_content,
+        text_content,
+        html_content,
+        &segments,
+    ) {
+        Ok(issue) => issue,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&form_url));
+        }
+    };
+    let attachment_ids = match parse_attachment_ids(&attachments) {
+        Ok(attachment_ids) => attachment_ids,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other(&form_url));
+        }
+    };
+
     let mut transaction = match idempotency::try_processing(&pool, &idempotency_key, *user_id)
         .await
         .map_err(utils::e500)?
@@ -50,18 +109,48 @@ pub async fn publish_newsletter(
             return Ok(res);
         }
     };
+    // Checked once the request is known to be new, as a retry finds the files already attached.
+    if let Some(e) = attachments_problem(&attachment_ids, &mut transaction)
+        .await
+        .map_err(utils::e500)?
+    {
+        FlashMessage::error(e).send();
+        return Ok(utils::see_other(&form_url));
+    }
+
+    let issue_id = insert_newsletter_issue(
+        &mut transaction,
+        *user_id,
+        &newsletter,
+        &title,
+        &body,
+        &segments,
+        track_engagement,
+    )
+    .await
+    .context("Failed to store newsletter issue details.")
+    .map_err(utils::e500)?;
 
-    let issue_id = insert_newsletter_issue(&mut transaction, &title, &text_content, &html_content)
+    attachments::attach_to_issue(&mut transaction, issue_id, &attachment_ids)
         .await
-        .context("Failed to store newsletter issue details.")
         .map_err(utils::e500)?;
 
-    enqueue_delivery_tasks(&mut transaction, issue_id)
+    enqueue_delivery_tasks(&mut transaction, issue_id, &newsletter, &segments)
         .await
         .context("Failed to enqueue delivery tasks.")
         .map_err(utils::e500)?;
 
-    let response = utils::see_other("/admin/newsletters");
+    audit::record_audit_event(
+        &mut *transaction,
+        *user_id,
+        AuditAction::PublishNewsletter,
+        Some(&issue_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    let response = utils::see_other(&form_url);
     let response = idempotency::save_response(transaction, &idempotency_key, *user_id, response)
         .await
         .map_err(utils::e500)?;
@@ -71,38 +160,148 @@ pub async fn publish_newsletter(
     Ok(response)
 }
 
+/// The publish form of the default newsletter lives at the bare URL.
+fn publish_form_url(newsletter: &Newsletter) -> String {
+    if newsletter.slug == newsletters::DEFAULT_NEWSLETTER {
+        "/admin/newsletters".into()
+    } else {
+        format!("/admin/newsletters?newsletter={}", newsletter.slug)
+    }
+}
+
+fn parse_issue(
+    title: String,
+    markdown_content: String,
+    text_content: String,
+    html_content: String,
+    segments: &[String],
+) -> Result<(NewsletterTitle, NewsletterBody, Vec<String>), String> {
+    let title = NewsletterTitle::parse(title)?;
+    let (text_content, html_content) = issue_content(markdown_content, text_content, html_content)
+        .ok_or("Write the issue in Markdown, or provide both its plain text and HTML content.")?;
+    let body = NewsletterBody::parse(text_content, html_content)?;
+    let mut segments = segments
+        .iter()
+        .map(|s| SubscriberTag::parse(s).map(|tag| tag.as_ref().to_owned()))
+        .collect::<Result<Vec<_>, _>>()?;
+    segments.sort();
+    segments.dedup();
+
+    Ok((title, body, segments))
+}
+
+fn parse_attachment_ids(attachments: &[String]) -> Result<Vec<Uuid>, String> {
+    let mut attachment_ids = attachments
+        .iter()
+        .map(|id| Uuid::parse_str(id).map_err(|_| format!("{} is not a valid attachment.", id)))
+        .collect::<Result<Vec<_>, _>>()?;
+    attachment_ids.sort();
+    attachment_ids.dedup();
+
+    Ok(attachment_ids)
+}
+
+/// Why the picked files cannot be sent with the issue, if they cannot. They must still be
+/// waiting for an issue, and fit in a single email.
+async fn attachments_problem(
+    attachment_ids: &[Uuid],
+    transaction: &mut Transaction<'_, Postgres>,
+) -> Result<Option<String>, anyhow::Error> {
+    if attachment_ids.is_empty() {
+        return Ok(None);
+    }
+
+    let (n_pending, size) =
+        attachments::measure_pending_attachments(attachment_ids, &mut **transaction).await?;
+    if n_pending != attachment_ids.len() {
+        Ok(Some(
+            "Some of the attachments have been deleted or sent with another issue.".into(),
+        ))
+    } else if size > attachments::MAX_ISSUE_ATTACHMENTS_SIZE {
+        Ok(Some(format!(
+            "The attachments of an issue cannot add up to more than {} MB.",
+            attachments::MAX_ISSUE_ATTACHMENTS_SIZE / 1024 / 1024
+        )))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Picks the plain text and HTML bodies of the issue. Content generated from Markdown can be
+/// overridden by filling in the explicit fields.
+fn issue_content(
+    markdown_content: String,
+    text_content: String,
+    html_content: String,
+) -> Option<(String, String)> {
+    let (generated_text, generated_html) = if markdown_content.trim().is_empty() {
+        (None, None)
+    } else {
+        let rendered = markdown::render(&markdown_content);
+        (Some(rendered.text), Some(rendered.html))
+    };
+
+    let text_content = non_empty(text_content).or(generated_text)?;
+    let html_content = non_empty(html_content).or(generated_html)?;
+
+    Some((text_content, html_content))
+}
+
+fn non_empty(content: String) -> Option<String> {
+    if content.trim().is_empty() {
+        None
+    } else {
+        Some(content)
+    }
+}
+
 #[tracing::instrument(skip_all)]
 async fn insert_newsletter_issue(
     transaction: &mut Transaction<'_, Postgres>,
-    title: &str,
-    text_content: &str,
-    html_content: &str,
+    author_id: Uuid,
+    newsletter: &Newsletter,
+    title: &NewsletterTitle,
+    body: &NewsletterBody,
+    segments: &[String],
+    tracking_enabled: bool,
 ) -> Result<Uuid, sqlx::Error> {
     let newsletter_issue_id = Uuid::new_v4();
     let query = sqlx::query!(
         r#"INSERT INTO newsletter_issues (
         newsletter_issue_id,
+        newsletter_id,
+        author_id,
         title,
         text_content,
         html_content,
+        segments,
+        tracking_enabled,
         published_at
     )
-    VALUES ($1, $2, $3, $4, now())
+    VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now())
     "#,
         newsletter_issue_id,
-        title,
-        text_content,
-        html_content
+        newsletter.newsletter_id,
+        author_id,
+        title.as_ref(),
+        body.text(),
+        body.html(),
+        segments,
+        tracking_enabled
     );
     transaction.execute(query).await?;
 
     Ok(newsletter_issue_id)
 }
 
+/// Queues the issue for every confirmed subscriber of the newsletter, or only for those with
+/// one of the segment tags if there are any.
 #[tracing::instrument(skip_all)]
 async fn enqueue_delivery_tasks(
     transaction: &mut Transaction<'_, Postgres>,
     newsletter_issue_id: Uuid,
+    newsletter: &Newsletter,
+    segments: &[String],
 ) -> Result<(), sqlx::Error> {
     let query = sqlx::query!(
         r#"INSERT INTO issue_delivery_queue (
@@ -110,9 +309,24 @@ async fn enqueue_delivery_tasks(
         subscriber_email
     )
     SELECT $1, email
-    FROM subscriptions
-    WHERE status = 'confirmed'"#,
-        newsletter_issue_id
+    FROM subscriptions s
+    WHERE status = 'confirmed'
+        AND newsletter_id = $3
+        AND (paused_until IS NULL OR paused_until <= now())
+        AND NOT EXISTS (
+            SELECT 1 FROM suppressions p
+            WHERE p.address IN (lower(s.email), split_part(lower(s.email), '@', 2))
+        )
+        AND (
+            cardinality($2::TEXT[]) = 0
+            OR EXISTS (
+                SELECT 1 FROM subscriber_tags t
+                WHERE t.subscriber_id = s.id AND t.tag = ANY($2)
+            )
+        )"#,
+        newsletter_issue_id,
+        segments,
+        newsletter.newsletter_id
     );
     transaction.execute(query).await?;
 
@@ -1,45 +1,24 @@
-use actix_web::{http::header::ContentType, Error, HttpResponse};
+use actix_web::{Error, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
-use std::fmt::Write;
+use askama::Template;
+
+use crate::{authentication, html, session_state::TypedSession};
+
+#[derive(Template)]
+#[template(path = "admin/change_password.html")]
+struct ChangePasswordPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    csrf_token: &'a str,
+}
 
 pub async fn change_password_form(
     flash_messages: IncomingFlashMessages,
+    session: TypedSession,
 ) -> Result<HttpResponse, Error> {
-    let mut msg_html = String::new();
-    for msg in flash_messages.iter() {
-        writeln!(msg_html, "<p><i>{}</i></p>", msg.content()).unwrap();
-    }
+    let csrf_token = authentication::csrf_token(&session)?;
 
-    Ok(HttpResponse::Ok().content_type(ContentType::html()).body(
-        format!(r#"<!DOCTYPE html>
-<html lang="en">
-    <head>
-        <meta http-equiv="content-type" content="text/html; charset=utf-8">
-        <title>Change password</title>
-    </head>
-    <body>
-        {msg_html}
-        <form action="/admin/dashboard" method="post">
-            <label>
-                Current password
-                <input type="password" placeholder="Enter your current password" name="current_password">
-            </label>
-            <br>
-            <label>
-                New password
-                <input type="password" placeholder="Enter new password" name="new_password">
-            </label>
-            <br>
-            <label>
-                Confirm new password
-                <input type="password" placeholder="Enter new password again" name="new_password_check">
-            </label>
-            <br>
-            <button type="submit">Change password</button>
-        </form>
-        <p><a href="/admin/dashboard">&lt;- Back</a></p>
-    </body>
-</html>
-    "#),
-    ))
+    html::render(&ChangePasswordPage {
+        flash_messages: &flash_messages,
+        csrf_token: &csrf_token,
+    })
 }
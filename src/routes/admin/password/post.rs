@@ -1,14 +1,16 @@
 use actix_web::{
     web::{Data, Form, ReqData},
-    Error, HttpResponse,
+    Error, HttpRequest, HttpResponse,
 };
 use actix_web_flash_messages::FlashMessage;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::PgPool;
 
 use crate::{
-    authentication::{self, AuthError, Credentials, UserId},
+    audit::{self, AuditAction},
+    authentication::{self, AuthError, Credentials, PasswordHashing, PasswordPolicy, UserId},
     routes::admin::dashboard,
+    session_state::TypedSession,
     utils,
 };
 
@@ -21,8 +23,12 @@ pub struct FormData {
 
 pub async fn change_password(
     form: Form<FormData>,
+    request: HttpRequest,
     pool: Data<PgPool>,
+    password_hashing: Data<PasswordHashing>,
+    password_policy: Data<PasswordPolicy>,
     user_id: ReqData<UserId>,
+    session: TypedSession,
 ) -> Result<HttpResponse, Error> {
     let user_id = user_id.into_inner();
 
@@ -37,11 +43,13 @@ pub async fn change_password(
         .map_err(utils::e500)?;
 
     let credentials = Credentials {
-        username,
-        password: form.0.current_password,
+        username: username.clone(),
+        password: form.current_password.clone(),
     };
 
-    if let Err(e) = authentication::validate_credentials(credentials, &pool).await {
+    if let Err(e) =
+        authentication::validate_credentials(credentials, &password_hashing, &pool).await
+    {
         return match e {
             AuthError::InvalidCredentials(_) => {
                 FlashMessage::error("The current password is incorrect.").send();
@@ -51,10 +59,39 @@ pub async fn change_password(
         };
     }
 
-    authentication::change_password(*user_id, form.0.new_password, &pool)
+    if let Err(e) = password_policy.check(&form.new_password, &form.current_password, &username) {
+        FlashMessage::error(e.to_string()).send();
+        return Ok(utils::see_other("/admin/password"));
+    }
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+
+    authentication::change_password(
+        *user_id,
+        form.0.new_password,
+        password_hashing.params(),
+        &mut *transaction,
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    let session_id = session.get_session_id().map_err(utils::e500)?;
+    authentication::revoke_other_sessions(session_id, *user_id, &mut *transaction)
         .await
         .map_err(utils::e500)?;
 
+    audit::record_audit_event(
+        &mut *transaction,
+        *user_id,
+        AuditAction::ChangePassword,
+        Some(&user_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    transaction.commit().await.map_err(utils::e500)?;
+
     FlashMessage::error("Your password has been changed.").send();
 
     Ok(utils::see_other("/admin/password"))
@@ -0,0 +1,46 @@
+use actix_web::{
+    web::{Data, ReqData},
+    Error, HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{self, ActiveSession, UserId},
+    configuration::SessionSettings,
+    html,
+    session_state::TypedSession,
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/sessions.html")]
+struct ActiveSessionsPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    sessions: Vec<ActiveSession>,
+    current_session_id: Option<&'a Uuid>,
+    csrf_token: &'a str,
+}
+
+pub async fn active_sessions(
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    settings: Data<SessionSettings>,
+    user_id: ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+    let current_session_id = session.get_session_id().map_err(utils::e500)?;
+    let sessions = authentication::get_active_sessions(**user_id, &settings, &pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&ActiveSessionsPage {
+        flash_messages: &flash_messages,
+        sessions,
+        current_session_id: current_session_id.as_ref(),
+        csrf_token: &csrf_token,
+    })
+}
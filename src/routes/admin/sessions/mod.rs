@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::active_sessions;
+pub use post::revoke_session;
@@ -0,0 +1,61 @@
+use actix_web::{
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    authentication::{self, UserId},
+    session_state::TypedSession,
+    utils,
+};
+
+#[derive(serde::Deserialize)]
+pub struct FormData {
+    session_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Revoke a session",
+    skip_all,
+    fields(user_id=%&*user_id, session_id=%form.session_id)
+)]
+pub async fn revoke_session(
+    form: Form<FormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let revoked = authentication::revoke_session(form.session_id, **user_id, &mut *transaction)
+        .await
+        .map_err(utils::e500)?;
+    if !revoked {
+        FlashMessage::error("The session does not exist or has already ended.").send();
+        return Ok(utils::see_other("/admin/sessions"));
+    }
+
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::RevokeSession,
+        Some(&form.session_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    if session.get_session_id().map_err(utils::e500)? == Some(form.session_id) {
+        session.log_out();
+        FlashMessage::info("You have successfully logged out.").send();
+        return Ok(utils::see_other("/login"));
+    }
+
+    FlashMessage::info("The session has been revoked.").send();
+    Ok(utils::see_other("/admin/sessions"))
+}
@@ -0,0 +1,62 @@
+use actix_web::{
+    web::{Data, Query},
+    Error, HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    authentication, html,
+    newsletters::{self, NewsletterSummary},
+    session_state::TypedSession,
+    subscribers::{self, SubscriberFilter, SubscriberSummary},
+    utils,
+};
+
+const MAX_SUBSCRIBERS: i64 = 100;
+
+#[derive(serde::Deserialize)]
+pub struct QueryParams {
+    newsletter: Option<String>,
+    email: Option<String>,
+}
+
+#[derive(Template)]
+#[template(path = "admin/subscribers.html")]
+struct SubscribersPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    subscribers: Vec<SubscriberSummary>,
+    newsletters: Vec<NewsletterSummary>,
+    selected_newsletter: &'a str,
+    email: &'a str,
+    csrf_token: &'a str,
+}
+
+pub async fn subscribers(
+    query: Query<QueryParams>,
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let filter = SubscriberFilter {
+        newsletter: utils::non_empty(&query.newsletter),
+        email: utils::non_empty(&query.email),
+    };
+    let subscribers = subscribers::get_subscribers(&filter, MAX_SUBSCRIBERS, &pool)
+        .await
+        .map_err(utils::e500)?;
+    let newsletters = newsletters::get_newsletters(&pool)
+        .await
+        .map_err(utils::e500)?;
+    let csrf_token = authentication::csrf_token(&session)?;
+
+    html::render(&SubscribersPage {
+        flash_messages: &flash_messages,
+        subscribers,
+        newsletters,
+        selected_newsletter: filter.newsletter.unwrap_or_default(),
+        email: filter.email.unwrap_or_default(),
+        csrf_token: &csrf_token,
+    })
+}
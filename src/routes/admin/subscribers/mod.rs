@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::subscribers;
+pub use post::update_subscriber_tags;
@@ -0,0 +1,67 @@
+use actix_web::{
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    authentication::UserId,
+    domain::SubscriberTag,
+    subscribers, utils,
+};
+
+#[derive(serde::Deserialize)]
+pub struct TagsFormData {
+    subscriber_id: Uuid,
+    tags: String,
+}
+
+#[tracing::instrument(
+    name = "Update the tags of a subscriber",
+    skip_all,
+    fields(user_id=%&*user_id, subscriber_id=%form.subscriber_id)
+)]
+pub async fn update_subscriber_tags(
+    form: Form<TagsFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let tags = match SubscriberTag::parse_list(&form.tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/subscribers"));
+        }
+    };
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let Some(email) = subscribers::get_subscriber_email(form.subscriber_id, &mut *transaction)
+        .await
+        .map_err(utils::e500)?
+    else {
+        FlashMessage::error("The subscriber does not exist.").send();
+        return Ok(utils::see_other("/admin/subscribers"));
+    };
+
+    subscribers::set_subscriber_tags(&mut transaction, form.subscriber_id, &tags)
+        .await
+        .map_err(utils::e500)?;
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::UpdateSubscriberTags,
+        Some(&form.subscriber_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!("The tags of {} have been updated.", email)).send();
+
+    Ok(utils::see_other("/admin/subscribers"))
+}
@@ -0,0 +1,36 @@
+use actix_web::{web::Data, Error, HttpResponse};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    authentication, html,
+    session_state::TypedSession,
+    suppressions::{self, Suppression},
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/suppressions.html")]
+struct SuppressionsPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    suppressions: Vec<Suppression>,
+    csrf_token: &'a str,
+}
+
+pub async fn suppression_list(
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+    let suppressions = suppressions::get_suppressions(&pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&SuppressionsPage {
+        flash_messages: &flash_messages,
+        suppressions,
+        csrf_token: &csrf_token,
+    })
+}
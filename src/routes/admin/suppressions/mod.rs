@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::suppression_list;
+pub use post::{create_suppression, delete_suppression};
@@ -0,0 +1,124 @@
+use actix_web::{
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    authentication::UserId,
+    domain::SuppressedAddress,
+    suppressions::{self, SuppressionSource},
+    utils,
+};
+
+#[derive(serde::Deserialize)]
+pub struct CreateFormData {
+    address: String,
+    reason: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct DeleteFormData {
+    suppression_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Create a suppression",
+    skip_all,
+    fields(user_id=%&*user_id, address=%form.address)
+)]
+pub async fn create_suppression(
+    form: Form<CreateFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let CreateFormData { address, reason } = form.into_inner();
+
+    let address = match SuppressedAddress::parse(address) {
+        Ok(address) => address,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/suppressions"));
+        }
+    };
+    let reason = reason.trim();
+    if reason.is_empty() || reason.graphemes(true).count() > 200 {
+        FlashMessage::error("The reason must be between 1 and 200 characters long.").send();
+        return Ok(utils::see_other("/admin/suppressions"));
+    }
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let created = suppressions::add_suppression(
+        &mut transaction,
+        &address,
+        reason,
+        SuppressionSource::Admin,
+        Some(**user_id),
+    )
+    .await
+    .map_err(utils::e500)?;
+    if created.is_none() {
+        FlashMessage::error(format!("{} is already suppressed.", address.as_ref())).send();
+        return Ok(utils::see_other("/admin/suppressions"));
+    }
+
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::CreateSuppression,
+        Some(address.as_ref()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!(
+        "Nothing will be sent to {} anymore.",
+        address.as_ref()
+    ))
+    .send();
+
+    Ok(utils::see_other("/admin/suppressions"))
+}
+
+#[tracing::instrument(
+    name = "Delete a suppression",
+    skip_all,
+    fields(user_id=%&*user_id, suppression_id=%form.suppression_id)
+)]
+pub async fn delete_suppression(
+    form: Form<DeleteFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let Some(address) = suppressions::remove_suppression(&mut transaction, form.suppression_id)
+        .await
+        .map_err(utils::e500)?
+    else {
+        FlashMessage::error("The suppression does not exist or has already been deleted.").send();
+        return Ok(utils::see_other("/admin/suppressions"));
+    };
+
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::DeleteSuppression,
+        Some(&address),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info(format!("{} is no longer suppressed.", address)).send();
+
+    Ok(utils::see_other("/admin/suppressions"))
+}
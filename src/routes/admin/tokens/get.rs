@@ -0,0 +1,56 @@
+use actix_web::{
+    web::{Data, ReqData},
+    Error, HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{self, ApiToken, ApiTokenScope, UserId},
+    html,
+    session_state::TypedSession,
+    utils,
+};
+
+#[derive(Template)]
+#[template(path = "admin/tokens.html")]
+struct ApiTokensPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    tokens: Vec<ApiToken>,
+    /// Only set right after a token is created, as the token cannot be shown again.
+    new_token: Option<&'a str>,
+    scopes: &'static [ApiTokenScope],
+    csrf_token: &'a str,
+}
+
+pub async fn api_tokens(
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    render_api_tokens_page(&flash_messages, None, **user_id, &pool, &session).await
+}
+
+pub(super) async fn render_api_tokens_page(
+    flash_messages: &IncomingFlashMessages,
+    new_token: Option<&str>,
+    user_id: Uuid,
+    pool: &PgPool,
+    session: &TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(session)?;
+    let tokens = authentication::get_api_tokens(user_id, pool)
+        .await
+        .map_err(utils::e500)?;
+
+    html::render(&ApiTokensPage {
+        flash_messages,
+        tokens,
+        new_token,
+        scopes: ApiTokenScope::ALL,
+        csrf_token: &csrf_token,
+    })
+}
@@ -0,0 +1,138 @@
+use actix_web::{
+    http::header::{HeaderValue, CACHE_CONTROL},
+    web::{Data, Form, ReqData},
+    Error, HttpRequest, HttpResponse,
+};
+use actix_web_flash_messages::{FlashMessage, IncomingFlashMessages};
+use actix_web_lab::extract::UrlEncodedForm;
+use secrecy::ExposeSecret;
+use sqlx::PgPool;
+use unicode_segmentation::UnicodeSegmentation;
+use uuid::Uuid;
+
+use crate::{
+    audit::{self, AuditAction},
+    authentication::{self, ApiTokenScope, UserId},
+    session_state::TypedSession,
+    utils,
+};
+
+use super::get;
+
+#[derive(serde::Deserialize)]
+pub struct CreateFormData {
+    name: String,
+    #[serde(default)]
+    scopes: Vec<String>,
+}
+
+#[derive(serde::Deserialize)]
+pub struct RevokeFormData {
+    token_id: Uuid,
+}
+
+#[tracing::instrument(
+    name = "Create an API token",
+    skip_all,
+    fields(user_id=%&*user_id)
+)]
+pub async fn create_api_token(
+    form: UrlEncodedForm<CreateFormData>,
+    request: HttpRequest,
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let CreateFormData { name, scopes } = form.0;
+
+    let name = name.trim();
+    if name.is_empty() || name.graphemes(true).count() > 100 {
+        FlashMessage::error("The token name must be between 1 and 100 characters long.").send();
+        return Ok(utils::see_other("/admin/tokens"));
+    }
+
+    let scopes = match scopes
+        .iter()
+        .map(|s| ApiTokenScope::parse(s))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(scopes) if !scopes.is_empty() => scopes,
+        Ok(_) => {
+            FlashMessage::error("Select at least one scope for the token.").send();
+            return Ok(utils::see_other("/admin/tokens"));
+        }
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(utils::see_other("/admin/tokens"));
+        }
+    };
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let (token_id, token) =
+        authentication::create_api_token(**user_id, name, &scopes, &mut *transaction)
+            .await
+            .map_err(utils::e500)?;
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::CreateApiToken,
+        Some(&token_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    // The token is shown in this response only. Passing it along in a flash message would
+    // store it in a cookie.
+    let mut response = get::render_api_tokens_page(
+        &flash_messages,
+        Some(token.expose_secret()),
+        **user_id,
+        &pool,
+        &session,
+    )
+    .await?;
+    response
+        .headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static("no-store"));
+
+    Ok(response)
+}
+
+#[tracing::instrument(
+    name = "Revoke an API token",
+    skip_all,
+    fields(user_id=%&*user_id, token_id=%form.token_id)
+)]
+pub async fn revoke_api_token(
+    form: Form<RevokeFormData>,
+    request: HttpRequest,
+    pool: Data<PgPool>,
+    user_id: ReqData<UserId>,
+) -> Result<HttpResponse, Error> {
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let revoked = authentication::revoke_api_token(form.token_id, **user_id, &mut *transaction)
+        .await
+        .map_err(utils::e500)?;
+    if !revoked {
+        FlashMessage::error("The API token does not exist or has already been revoked.").send();
+        return Ok(utils::see_other("/admin/tokens"));
+    }
+
+    audit::record_audit_event(
+        &mut *transaction,
+        **user_id,
+        AuditAction::RevokeApiToken,
+        Some(&form.token_id.to_string()),
+        utils::client_ip(&request).as_deref(),
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info("The API token has been revoked.").send();
+
+    Ok(utils::see_other("/admin/tokens"))
+}
@@ -0,0 +1,91 @@
+use actix_web::{
+    web::{Data, Json},
+    Error, HttpResponse,
+};
+use sqlx::PgPool;
+
+use crate::{
+    domain::SuppressedAddress,
+    email_events::{self, EmailEvent, EmailEventKind},
+    suppressions::{self, SuppressionSource},
+    utils,
+};
+
+/// Bounce types for addresses that will never accept an email.
+const SUPPRESSED_BOUNCE_TYPES: &[&str] = &["HardBounce", "BadEmailAddress", "ManuallyDeactivated"];
+
+/// The fields shared by Postmark's bounce and spam complaint webhooks. Other webhooks have
+/// different fields, so only the record type is required to read them.
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkEvent {
+    #[serde(default)]
+    record_type: String,
+    #[serde(rename = "ID")]
+    id: Option<i64>,
+    #[serde(rename = "Type")]
+    bounce_type: Option<String>,
+    email: Option<String>,
+    description: Option<String>,
+}
+
+/// Records the bounces and spam complaints Postmark reports. Addresses that bounced for good or
+/// complained are suppressed, so that no further issues are sent to them.
+#[tracing::instrument(
+    name = "Receive a Postmark webhook",
+    skip_all,
+    fields(record_type=%payload.record_type, provider_event_id=?payload.id)
+)]
+pub async fn postmark_webhook(
+    payload: Json<PostmarkEvent>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let kind = match payload.record_type.as_str() {
+        "Bounce" => EmailEventKind::Bounce,
+        "SpamComplaint" => EmailEventKind::SpamComplaint,
+        // Postmark stops retrying once we answer, so other records are acknowledged.
+        _ => return Ok(HttpResponse::Ok().finish()),
+    };
+    let (Some(id), Some(email)) = (payload.id, payload.email.as_deref()) else {
+        return Err(utils::e400(
+            "Bounces and spam complaints need an ID and an Email.",
+        ));
+    };
+    let event = EmailEvent {
+        provider_event_id: id,
+        kind,
+        bounce_type: payload.bounce_type.as_deref(),
+        email,
+        description: payload.description.as_deref(),
+    };
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let is_new = email_events::record_email_event(&event, &mut *transaction)
+        .await
+        .map_err(utils::e500)?;
+    let source = match kind {
+        EmailEventKind::SpamComplaint => Some(SuppressionSource::SpamComplaint),
+        EmailEventKind::Bounce
+            if event
+                .bounce_type
+                .is_some_and(|t| SUPPRESSED_BOUNCE_TYPES.contains(&t)) =>
+        {
+            Some(SuppressionSource::Bounce)
+        }
+        EmailEventKind::Bounce => None,
+    };
+    if let (true, Some(source)) = (is_new, source) {
+        match SuppressedAddress::parse(email.to_owned()) {
+            Ok(address) => {
+                let reason = event.description.unwrap_or(source.as_str());
+                suppressions::add_suppression(&mut transaction, &address, reason, source, None)
+                    .await
+                    .map_err(utils::e500)?;
+            }
+            Err(e) => tracing::warn!(error.message = %e, "Cannot suppress an invalid address."),
+        }
+    }
+    transaction.commit().await.map_err(utils::e500)?;
+
+    Ok(HttpResponse::Ok().finish())
+}
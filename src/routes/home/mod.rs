@@ -1,7 +1,18 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{Error, HttpResponse};
+use askama::Template;
 
-pub async fn home() -> HttpResponse {
+use crate::html;
+
+#[derive(Template)]
+#[template(path = "home.html")]
+struct HomePage;
+
+pub async fn home() -> Result<HttpResponse, Error> {
+    html::render(&HomePage)
+}
+
+pub async fn stylesheet() -> HttpResponse {
     HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(include_str!("home.html"))
+        .content_type("text/css; charset=utf-8")
+        .body(include_str!("../../../static/style.css"))
 }
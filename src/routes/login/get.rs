@@ -1,36 +1,24 @@
-use actix_web::{http::header::ContentType, HttpResponse};
+use actix_web::{Error, HttpResponse};
 use actix_web_flash_messages::IncomingFlashMessages;
-use std::fmt::Write;
+use askama::Template;
 
-pub async fn login_form(flash_messages: IncomingFlashMessages) -> HttpResponse {
-    let mut error_html = String::new();
-    for msg in flash_messages.iter() {
-        writeln!(error_html, "<p><i>{}</i></p>", msg.content()).unwrap();
-    }
+use crate::{authentication, html, session_state::TypedSession};
 
-    HttpResponse::Ok()
-        .content_type(ContentType::html())
-        .body(format!(
-            r#"<!DOCTYPE html>
-<html lang="en">
-    <head>
-        <meta http-equiv="content-type" content="text/html; charset=utf-8">
-        <title>Login</title>
-    </head>
-    <body>
-        {error_html}
-        <form action="/login" method="post">
-            <label>
-                Username
-                <input type="text" placeholder="Enter username" name="username">
-            </label>
-            <label>
-                Password
-                <input type="password" placeholder="Enter password" name="password">
-            </label>
-            <button type="submit">Login</button>
-        </form>
-    </body>
-</html>"#
-        ))
+#[derive(Template)]
+#[template(path = "login.html")]
+struct LoginPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    csrf_token: &'a str,
+}
+
+pub async fn login_form(
+    flash_messages: IncomingFlashMessages,
+    session: TypedSession,
+) -> Result<HttpResponse, Error> {
+    let csrf_token = authentication::csrf_token(&session)?;
+
+    html::render(&LoginPage {
+        flash_messages: &flash_messages,
+        csrf_token: &csrf_token,
+    })
 }
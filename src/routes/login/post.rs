@@ -1,20 +1,23 @@
 use actix_web::{
     error::InternalError,
-    http::header::LOCATION,
+    http::header::{LOCATION, USER_AGENT},
     web::{Data, Form},
-    HttpResponse,
+    HttpRequest, HttpResponse,
 };
 use actix_web_flash_messages::FlashMessage;
-use anyhow::Error;
+use anyhow::{Context, Error};
 use secrecy::Secret;
 use sqlx::PgPool;
 use std::fmt;
 use tracing::{field, Span};
+use uuid::Uuid;
 
 use crate::{
-    authentication::{self, AuthError, Credentials},
+    audit::{self, AuditAction},
+    authentication::{self, AuthError, Credentials, PasswordHashing},
     routes,
     session_state::TypedSession,
+    utils,
 };
 
 #[derive(serde::Deserialize)]
@@ -38,12 +41,14 @@ impl fmt::Debug for LoginError {
 }
 
 #[tracing::instrument(
-    skip(form, pool, session),
+    skip(form, request, pool, password_hashing, session),
     fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
 )]
 pub async fn login(
     form: Form<FormData>,
+    request: HttpRequest,
     pool: Data<PgPool>,
+    password_hashing: Data<PasswordHashing>,
     session: TypedSession,
 ) -> Result<HttpResponse, InternalError<LoginError>> {
     let credentials = Credentials {
@@ -52,15 +57,27 @@ pub async fn login(
     };
     Span::current().record("username", field::display(&credentials.username));
 
-    match authentication::validate_credentials(credentials, &pool).await {
+    match authentication::validate_credentials(credentials, &password_hashing, &pool).await {
         Ok(user_id) => {
             Span::current().record("user_id", field::display(&user_id));
 
             session.renew();
 
+            let ip_address = utils::client_ip(&request);
+            let user_agent = request
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|h| h.to_str().ok());
+            let session_id = start_session(user_id, ip_address.as_deref(), user_agent, &pool)
+                .await
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e)))?;
+
             session
                 .insert_user_id(user_id)
                 .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
+            session
+                .insert_session_id(session_id)
+                .map_err(|e| login_redirect(LoginError::UnexpectedError(e.into())))?;
 
             Ok(HttpResponse::SeeOther()
                 .insert_header((LOCATION, "/admin/dashboard"))
@@ -77,6 +94,37 @@ pub async fn login(
     }
 }
 
+async fn start_session(
+    user_id: Uuid,
+    ip_address: Option<&str>,
+    user_agent: Option<&str>,
+    pool: &PgPool,
+) -> Result<Uuid, Error> {
+    let mut transaction = pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool.")?;
+
+    let session_id =
+        authentication::register_session(user_id, ip_address, user_agent, &mut *transaction)
+            .await?;
+    audit::record_audit_event(
+        &mut *transaction,
+        user_id,
+        AuditAction::LogIn,
+        Some(&session_id.to_string()),
+        ip_address,
+    )
+    .await?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit the new session.")?;
+
+    Ok(session_id)
+}
+
 fn login_redirect(e: LoginError) -> InternalError<LoginError> {
     FlashMessage::error(e.to_string()).send();
 
@@ -2,12 +2,18 @@ mod admin;
 mod health_check;
 mod home;
 mod login;
+mod preferences;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod tracking;
 
 pub use admin::*;
 pub use health_check::*;
 pub use home::*;
 pub use login::*;
+pub use preferences::*;
 pub use subscriptions::*;
 pub use subscriptions_confirm::*;
+pub use subscriptions_unsubscribe::*;
+pub use tracking::*;
@@ -0,0 +1,43 @@
+use actix_web::{
+    web::{Data, Query},
+    Error, HttpResponse,
+};
+use actix_web_flash_messages::IncomingFlashMessages;
+use askama::Template;
+use sqlx::PgPool;
+
+use crate::{
+    html,
+    subscribers::{self, SubscriberPreferences},
+    utils,
+};
+
+#[derive(serde::Deserialize)]
+pub struct PreferencesParameters {
+    token: String,
+}
+
+#[derive(Template)]
+#[template(path = "preferences.html")]
+struct PreferencesPage<'a> {
+    flash_messages: &'a IncomingFlashMessages,
+    subscriber: SubscriberPreferences,
+    token: &'a str,
+}
+
+pub async fn preferences(
+    parameters: Query<PreferencesParameters>,
+    flash_messages: IncomingFlashMessages,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = subscribers::get_subscriber_preferences(&parameters.token, pool.get_ref())
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| utils::e401("The link is not valid."))?;
+
+    html::render(&PreferencesPage {
+        flash_messages: &flash_messages,
+        subscriber,
+        token: &parameters.token,
+    })
+}
@@ -0,0 +1,5 @@
+mod get;
+mod post;
+
+pub use get::preferences;
+pub use post::*;
@@ -0,0 +1,244 @@
+use actix_web::{
+    web::{Data, Form},
+    Error, HttpResponse,
+};
+use actix_web_flash_messages::FlashMessage;
+use chrono::{Duration, Utc};
+use sqlx::PgPool;
+
+use crate::{
+    domain::{SubscriberEmail, SubscriberName, SubscriberTag},
+    email_client::EmailTransport,
+    newsletters,
+    routes::subscriptions::{send_confirmation_email, store_token},
+    startup::ApplicationBaseUrl,
+    subscribers::{self, SubscriberPreferences},
+    suppressions, utils,
+};
+
+const MAX_PAUSE_WEEKS: i64 = 52;
+
+#[derive(serde::Deserialize)]
+pub struct NameFormData {
+    token: String,
+    name: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EmailFormData {
+    token: String,
+    email: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TagsFormData {
+    token: String,
+    /// Comma-separated.
+    tags: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct PauseFormData {
+    token: String,
+    /// Zero resumes delivery.
+    weeks: i64,
+}
+
+#[derive(serde::Deserialize)]
+pub struct TokenFormData {
+    token: String,
+}
+
+#[tracing::instrument(name = "Change the name of a subscriber", skip_all)]
+pub async fn change_name(
+    form: Form<NameFormData>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = get_subscriber(&form.token, &pool).await?;
+    let form = form.into_inner();
+    let response = utils::see_other(&preferences_url(&form.token));
+
+    let name = match SubscriberName::parse(form.name) {
+        Ok(name) => name,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(response);
+        }
+    };
+    subscribers::update_subscriber_name(subscriber.subscriber_id, &name, pool.get_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    FlashMessage::info("Your name has been updated.").send();
+
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Change the email of a subscriber", skip_all)]
+pub async fn change_email(
+    form: Form<EmailFormData>,
+    pool: Data<PgPool>,
+    email_client: Data<dyn EmailTransport>,
+    base_url: Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = get_subscriber(&form.token, &pool).await?;
+    let form = form.into_inner();
+    let response = utils::see_other(&preferences_url(&form.token));
+
+    let email = match SubscriberEmail::parse(form.email) {
+        Ok(email) => email,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(response);
+        }
+    };
+    if email.as_ref() == subscriber.email {
+        FlashMessage::info("That is already your email address.").send();
+        return Ok(response);
+    }
+    if subscriber.status == "suppressed" {
+        FlashMessage::error("Issues can no longer be delivered to this subscription.").send();
+        return Ok(response);
+    }
+
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    let newsletter = newsletters::get_newsletter(&subscriber.newsletter_slug, &mut *transaction)
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| utils::e500("The newsletter of the subscriber does not exist."))?;
+    // Whether the address is already subscribed or suppressed is not revealed.
+    let is_suppressed = suppressions::is_suppressed(&email, &mut *transaction)
+        .await
+        .map_err(utils::e500)?;
+    if is_suppressed
+        || !subscribers::change_subscriber_email(&mut transaction, subscriber.subscriber_id, &email)
+            .await
+            .map_err(utils::e500)?
+    {
+        FlashMessage::error("That email address cannot be used for this newsletter.").send();
+        return Ok(response);
+    }
+    // The new address is confirmed if they subscribe again.
+    if subscriber.status == "unsubscribed" {
+        transaction.commit().await.map_err(utils::e500)?;
+        FlashMessage::info("Your email address has been updated.").send();
+        return Ok(response);
+    }
+    let subscription_token = utils::generate_token();
+    store_token(
+        &mut transaction,
+        subscriber.subscriber_id,
+        &subscription_token,
+    )
+    .await
+    .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    send_confirmation_email(
+        email_client.get_ref(),
+        &newsletter,
+        &email,
+        &base_url.0,
+        &subscription_token,
+    )
+    .await
+    .map_err(utils::e500)?;
+
+    FlashMessage::info(format!(
+        "We have sent a confirmation link to {}. Issues will be delivered there once you confirm it.",
+        email.as_ref()
+    ))
+    .send();
+
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Change the tags of a subscriber", skip_all)]
+pub async fn change_tags(
+    form: Form<TagsFormData>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = get_subscriber(&form.token, &pool).await?;
+    let response = utils::see_other(&preferences_url(&form.token));
+
+    let tags = match SubscriberTag::parse_list(&form.tags) {
+        Ok(tags) => tags,
+        Err(e) => {
+            FlashMessage::error(e).send();
+            return Ok(response);
+        }
+    };
+    let mut transaction = pool.begin().await.map_err(utils::e500)?;
+    subscribers::set_subscriber_tags(&mut transaction, subscriber.subscriber_id, &tags)
+        .await
+        .map_err(utils::e500)?;
+    transaction.commit().await.map_err(utils::e500)?;
+
+    FlashMessage::info("Your topics have been updated.").send();
+
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Pause delivery to a subscriber", skip_all)]
+pub async fn pause_delivery(
+    form: Form<PauseFormData>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = get_subscriber(&form.token, &pool).await?;
+    let response = utils::see_other(&preferences_url(&form.token));
+
+    if !(0..=MAX_PAUSE_WEEKS).contains(&form.weeks) {
+        FlashMessage::error(format!(
+            "Delivery can be paused for up to {} weeks.",
+            MAX_PAUSE_WEEKS
+        ))
+        .send();
+        return Ok(response);
+    }
+    let paused_until = (form.weeks > 0).then(|| Utc::now() + Duration::weeks(form.weeks));
+    subscribers::pause_subscriber(subscriber.subscriber_id, paused_until, pool.get_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    match paused_until {
+        Some(paused_until) => FlashMessage::info(format!(
+            "Delivery is paused until {}.",
+            paused_until.format("%Y-%m-%d")
+        )),
+        None => FlashMessage::info("Delivery has been resumed."),
+    }
+    .send();
+
+    Ok(response)
+}
+
+#[tracing::instrument(name = "Unsubscribe from the preference page", skip_all)]
+pub async fn unsubscribe_from_newsletter(
+    form: Form<TokenFormData>,
+    pool: Data<PgPool>,
+) -> Result<HttpResponse, Error> {
+    let subscriber = get_subscriber(&form.token, &pool).await?;
+
+    subscribers::unsubscribe_subscriber(subscriber.subscriber_id, pool.get_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    FlashMessage::info(format!(
+        "You have been unsubscribed from {}.",
+        subscriber.newsletter
+    ))
+    .send();
+
+    Ok(utils::see_other(&preferences_url(&form.token)))
+}
+
+async fn get_subscriber(token: &str, pool: &PgPool) -> Result<SubscriberPreferences, Error> {
+    subscribers::get_subscriber_preferences(token, pool)
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| utils::e401("The link is not valid."))
+}
+
+fn preferences_url(token: &str) -> String {
+    format!("/preferences?token={}", token)
+}
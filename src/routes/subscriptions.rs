@@ -1,26 +1,31 @@
-use std::{error, fmt, iter};
+use std::{error, fmt};
 
 use actix_web::{
     http::StatusCode,
-    web::{Data, Form},
+    web::{Data, Path},
     HttpResponse,
 };
+use actix_web_lab::extract::UrlEncodedForm;
 use anyhow::Context;
 use chrono::Utc;
-use rand::{distributions::Alphanumeric, Rng};
 use sqlx::{Executor, PgPool, Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
-    email_client::EmailClient,
+    domain::{NewSubscriber, SubscriberEmail, SubscriberName, SubscriberTag},
+    email_client::EmailTransport,
+    html,
+    newsletters::{self, Newsletter},
     startup::ApplicationBaseUrl,
+    subscribers, suppressions, utils,
 };
 
 #[derive(thiserror::Error)]
 pub enum SubscribeError {
     #[error("{0}")]
     ValidationError(String),
+    #[error("There is no newsletter called {0}.")]
+    UnknownNewsletter(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -35,6 +40,7 @@ impl actix_web::ResponseError for SubscribeError {
     fn status_code(&self) -> StatusCode {
         match self {
             Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::UnknownNewsletter(_) => StatusCode::NOT_FOUND,
             Self::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
@@ -67,6 +73,8 @@ impl error::Error for StoreTokenError {
 pub struct FormData {
     email: String,
     name: String,
+    #[serde(default)]
+    tags: Vec<String>,
 }
 
 impl TryFrom<FormData> for NewSubscriber {
@@ -75,11 +83,45 @@ impl TryFrom<FormData> for NewSubscriber {
     fn try_from(value: FormData) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(value.name)?;
         let email = SubscriberEmail::parse(value.email)?;
-
-        Ok(NewSubscriber { name, email })
+        let mut tags = value
+            .tags
+            .iter()
+            .map(|tag| SubscriberTag::parse(tag))
+            .collect::<Result<Vec<_>, _>>()?;
+        tags.sort();
+        tags.dedup();
+
+        Ok(NewSubscriber { name, email, tags })
     }
 }
 
+/// Subscribes to the default newsletter.
+pub async fn subscribe(
+    form: UrlEncodedForm<FormData>,
+    pool: Data<PgPool>,
+    email_client: Data<dyn EmailTransport>,
+    base_url: Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, SubscribeError> {
+    subscribe_to(
+        newsletters::DEFAULT_NEWSLETTER,
+        form.0,
+        &pool,
+        email_client.get_ref(),
+        &base_url.0,
+    )
+    .await
+}
+
+pub async fn subscribe_to_newsletter(
+    slug: Path<String>,
+    form: UrlEncodedForm<FormData>,
+    pool: Data<PgPool>,
+    email_client: Data<dyn EmailTransport>,
+    base_url: Data<ApplicationBaseUrl>,
+) -> Result<HttpResponse, SubscribeError> {
+    subscribe_to(&slug, form.0, &pool, email_client.get_ref(), &base_url.0).await
+}
+
 #[tracing::instrument(
     name = "Adding a new subscriber",
     skip(form, pool, email_client, base_url),
@@ -88,24 +130,36 @@ impl TryFrom<FormData> for NewSubscriber {
         subscriber_name = %form.name
     )
 )]
-pub async fn subscribe(
-    form: Form<FormData>,
-    pool: Data<PgPool>,
-    email_client: Data<EmailClient>,
-    base_url: Data<ApplicationBaseUrl>,
+async fn subscribe_to(
+    newsletter: &str,
+    form: FormData,
+    pool: &PgPool,
+    email_client: &dyn EmailTransport,
+    base_url: &str,
 ) -> Result<HttpResponse, SubscribeError> {
-    let new_subscriber = form.0.try_into().map_err(SubscribeError::ValidationError)?;
+    let new_subscriber: NewSubscriber = form.try_into().map_err(SubscribeError::ValidationError)?;
 
     let mut transaction = pool
         .begin()
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let newsletter = newsletters::get_newsletter(newsletter, &mut *transaction)
+        .await?
+        .ok_or_else(|| SubscribeError::UnknownNewsletter(newsletter.to_owned()))?;
+
+    // Answer as if the subscription went through, so that the list is not revealed.
+    if suppressions::is_suppressed(&new_subscriber.email, &mut *transaction).await? {
+        tracing::info!("Ignoring a subscription from an address on the suppression list.");
+        return Ok(HttpResponse::Ok().finish());
+    }
+
+    let subscriber_id = insert_subscriber(&mut transaction, &newsletter, &new_subscriber)
         .await
         .context("Failed to insert a new subscriber in the database.")?;
+    subscribers::set_subscriber_tags(&mut transaction, subscriber_id, &new_subscriber.tags).await?;
 
-    let subscription_token = generate_subscription_token();
+    let subscription_token = utils::generate_token();
     store_token(&mut transaction, subscriber_id, &subscription_token)
         .await
         .context("Failed to store the confirmation token for a new subscriber.")?;
@@ -116,9 +170,10 @@ pub async fn subscribe(
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
     send_confirmation_email(
-        &email_client,
-        new_subscriber,
-        &base_url.0,
+        email_client,
+        &newsletter,
+        &new_subscriber.email,
+        base_url,
         &subscription_token,
     )
     .await
@@ -131,7 +186,7 @@ pub async fn subscribe(
     name = "Store subscription token in the database",
     skip(transaction, subscription_token)
 )]
-async fn store_token(
+pub(crate) async fn store_token(
     transaction: &mut Transaction<'_, Postgres>,
     subscriber_id: Uuid,
     subscription_token: &str,
@@ -150,30 +205,43 @@ async fn store_token(
 
 #[tracing::instrument(
     name = "Send a confirmation email to a new subscriber",
-    skip(email_client, new_subscriber, base_url)
+    skip(email_client, newsletter, recipient, base_url)
 )]
-async fn send_confirmation_email(
-    email_client: &EmailClient,
-    new_subscriber: NewSubscriber,
+pub(crate) async fn send_confirmation_email(
+    email_client: &dyn EmailTransport,
+    newsletter: &Newsletter,
+    recipient: &SubscriberEmail,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), anyhow::Error> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
     );
     let html_body = &format!(
-        "Welcome to our newsletter!<br /> Click <a href=\"{}\">here</a> to confirm your subscription.",
+        "Welcome to {}!<br /> Click <a href=\"{}\">here</a> to confirm your subscription.",
+        html::escape(&newsletter.name),
         confirmation_link
     );
     let text_body = &format!(
-        "Welcome to our newsletter!\nVisit {} to confirm your subscription.",
-        confirmation_link
+        "Welcome to {}!\nVisit {} to confirm your subscription.",
+        newsletter.name, confirmation_link
     );
 
-    email_client
-        .send_email(&new_subscriber.email, "Welcome!", html_body, text_body)
-        .await
+    match newsletter.sender().map_err(anyhow::Error::msg)? {
+        Some(sender) => {
+            email_client
+                .send_email_from(&sender, recipient, "Welcome!", html_body, text_body)
+                .await?
+        }
+        None => {
+            email_client
+                .send_email(recipient, "Welcome!", html_body, text_body)
+                .await?
+        }
+    }
+
+    Ok(())
 }
 
 #[tracing::instrument(
@@ -182,19 +250,30 @@ async fn send_confirmation_email(
 )]
 async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
+    newsletter: &Newsletter,
     new_subscriber: &NewSubscriber,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
 
     let query = sqlx::query!(
         r#"
-    INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-    VALUES ($1, $2, $3, $4, 'pending_confirmation')
-    "#X,
+    INSERT INTO subscriptions (
+        id,
+        newsletter_id,
+        email,
+        name,
+        subscribed_at,
+        status,
+        unsubscribe_token
+    )
+    VALUES ($1, $2, $3, $4, $5, 'pending_confirmation', $6)
+    "#,
         subscriber_id,
+        newsletter.newsletter_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        utils::generate_token()
     );
 
     transaction.execute(query).await?;
@@ -202,14 +281,6 @@ async fn insert_subscriber(
     Ok(subscriber_id)
 }
 
-fn generate_subscription_token() -> String {
-    let mut rng = rand::thread_rng();
-    iter::repeat_with(|| rng.sample(Alphanumeric))
-        .map(char::from)
-        .take(25)
-        .collect()
-}
-
 pub fn error_chain_fmt(e: &impl error::Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     writeln!(f, "{}\n", e)?;
 
@@ -0,0 +1,42 @@
+use actix_web::{
+    web::{Data, Query},
+    HttpResponse,
+};
+use sqlx::{Error, PgPool};
+
+#[derive(serde::Deserialize)]
+pub struct UnsubscribeParameters {
+    unsubscribe_token: String,
+}
+
+#[tracing::instrument(name = "Unsubscribe a subscriber", skip(parameters, pool))]
+pub async fn unsubscribe(
+    parameters: Query<UnsubscribeParameters>,
+    pool: Data<PgPool>,
+) -> HttpResponse {
+    match unsubscribe_subscriber(&pool, &parameters.unsubscribe_token).await {
+        Ok(true) => HttpResponse::Ok().finish(),
+        Ok(false) => HttpResponse::Unauthorized().finish(),
+        Err(_) => HttpResponse::InternalServerError().finish(),
+    }
+}
+
+/// Returns whether a subscriber matched the token.
+#[tracing::instrument(
+    name = "Mark subscriber as unsubscribed",
+    skip(pool, unsubscribe_token)
+)]
+async fn unsubscribe_subscriber(pool: &PgPool, unsubscribe_token: &str) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"UPDATE subscriptions SET status = 'unsubscribed' WHERE unsubscribe_token = $1"#,
+        unsubscribe_token
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to execute query: {:?}", e);
+        e
+    })?;
+
+    Ok(result.rows_affected() > 0)
+}
@@ -0,0 +1,48 @@
+use actix_web::{
+    http::header::{CacheControl, CacheDirective, LOCATION},
+    web::{Data, Path},
+    Error, HttpResponse,
+};
+use sqlx::PgPool;
+
+use crate::{tracking, utils};
+
+/// A transparent 1x1 GIF.
+const PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// Serves the tracking pixel of an issue, recording that the recipient opened it. The pixel is
+/// served for unknown tokens too, so that email clients don't show a broken image.
+#[tracing::instrument(name = "Track an open", skip_all)]
+pub async fn track_open(token: Path<String>, pool: Data<PgPool>) -> Result<HttpResponse, Error> {
+    tracking::record_open(&token, pool.get_ref())
+        .await
+        .map_err(utils::e500)?;
+
+    Ok(HttpResponse::Ok()
+        .content_type("image/gif")
+        .insert_header(CacheControl(vec![CacheDirective::NoStore]))
+        .body(PIXEL))
+}
+
+/// Records a click on a link of an issue and redirects to the link. The token is the
+/// recipient's tracking token followed by the position of the link, e.g. `a1b2c3-0`.
+#[tracing::instrument(name = "Track a click", skip_all)]
+pub async fn track_click(token: Path<String>, pool: Data<PgPool>) -> Result<HttpResponse, Error> {
+    let (token, position) = token
+        .rsplit_once('-')
+        .and_then(|(token, position)| Some((token, position.parse::<i16>().ok()?)))
+        .ok_or_else(|| utils::e404("The link does not exist."))?;
+
+    let url = tracking::record_click(token, position, &pool)
+        .await
+        .map_err(utils::e500)?
+        .ok_or_else(|| utils::e404("The link does not exist."))?;
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, url))
+        .finish())
+}
@@ -0,0 +1,98 @@
+use actix_web::{
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header::HeaderMap,
+    web::Data,
+    Error,
+};
+use actix_web_lab::middleware::Next;
+
+use crate::utils;
+
+/// The security headers added to every response. Pages that handle credentials, admin actions
+/// or subscriber preferences get a stricter set.
+#[derive(Clone, Debug)]
+pub struct SecurityHeaders {
+    default: HeaderMap,
+    restricted: HeaderMap,
+}
+
+impl SecurityHeaders {
+    pub fn new(default: HeaderMap, restricted: HeaderMap) -> Self {
+        Self {
+            default,
+            restricted,
+        }
+    }
+
+    fn for_path(&self, path: &str) -> &HeaderMap {
+        if is_restricted_path(path) {
+            &self.restricted
+        } else {
+            &self.default
+        }
+    }
+}
+
+/// Adds the configured security headers to the response, including error responses raised by
+/// inner middleware. Headers already set by a handler are left untouched.
+pub async fn add_security_headers(
+    req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let security_headers = req
+        .app_data::<Data<SecurityHeaders>>()
+        .cloned()
+        .ok_or_else(|| utils::e500("The security headers are not registered."))?;
+    let headers = security_headers.for_path(req.path()).clone();
+
+    match next.call(req).await {
+        Ok(mut response) => {
+            insert_missing(&headers, response.headers_mut());
+            Ok(response)
+        }
+        Err(e) => {
+            let mut response = e.error_response();
+            insert_missing(&headers, response.headers_mut());
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+fn insert_missing(headers: &HeaderMap, target: &mut HeaderMap) {
+    for (name, value) in headers {
+        if !target.contains_key(name) {
+            target.insert(name.clone(), value.clone());
+        }
+    }
+}
+
+fn is_restricted_path(path: &str) -> bool {
+    path == "/login"
+        || path == "/admin"
+        || path.starts_with("/admin/")
+        || path == "/preferences"
+        || path.starts_with("/preferences/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::is_restricted_path;
+
+    #[test]
+    fn login_admin_and_preference_pages_are_restricted() {
+        assert!(is_restricted_path("/login"));
+        assert!(is_restricted_path("/admin"));
+        assert!(is_restricted_path("/admin/dashboard"));
+        assert!(is_restricted_path("/preferences"));
+        assert!(is_restricted_path("/preferences/email"));
+    }
+
+    #[test]
+    fn public_pages_are_not_restricted() {
+        assert!(!is_restricted_path("/"));
+        assert!(!is_restricted_path("/subscriptions/confirm"));
+        assert!(!is_restricted_path("/administrators"));
+    }
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use anyhow::Error;
+use sqlx::PgPool;
+
+/// The first pause after the email provider reports that we send too fast.
+const INITIAL_BACKOFF_SECONDS: i32 = 5;
+/// The pause doubles every time the provider complains again, up to this long.
+const MAX_BACKOFF_SECONDS: i32 = 300;
+
+pub enum Permit {
+    /// The number of emails that can be sent right away.
+    Granted(usize),
+    /// Nothing can be sent before this much time has passed.
+    Wait(Duration),
+}
+
+/// Limits how fast emails are sent. The limit is shared through the database by every delivery
+/// worker, however many processes they run in.
+#[derive(Clone, Copy, Debug)]
+pub struct SendRateLimiter {
+    /// `None` when only the provider decides how fast we send.
+    messages_per_second: Option<f64>,
+    /// The most messages the budget holds.
+    burst: f64,
+}
+
+impl SendRateLimiter {
+    pub fn new(messages_per_minute: Option<u32>, burst: u32) -> Self {
+        Self {
+            messages_per_second: messages_per_minute.map(|limit| f64::from(limit) / 60.0),
+            burst: f64::from(burst.max(1)),
+        }
+    }
+
+    /// Takes up to `wanted` messages from the shared budget. The budget refills continuously
+    /// and holds at most `burst` messages, so that idle workers cannot save up past it.
+    #[tracing::instrument(skip(self, pool))]
+    pub async fn acquire(&self, pool: &PgPool, wanted: usize) -> Result<Permit, Error> {
+        let mut transaction = pool.begin().await?;
+
+        let bucket = sqlx::query!(
+            r#"SELECT tokens, refilled_at, paused_until, now() AS "now!"
+            FROM send_rate_limit
+            FOR UPDATE"#
+        )
+        .fetch_one(&mut *transaction)
+        .await?;
+        if let Some(paused_until) = bucket.paused_until.filter(|&p| p > bucket.now) {
+            return Ok(Permit::Wait((paused_until - bucket.now).to_std()?));
+        }
+        let Some(rate) = self.messages_per_second else {
+            return Ok(Permit::Granted(wanted));
+        };
+
+        let capacity = self.burst;
+        let tokens = match bucket.refilled_at {
+            Some(refilled_at) => {
+                let elapsed = (bucket.now - refilled_at).num_milliseconds() as f64 / 1000.0;
+                (bucket.tokens + elapsed * rate).min(capacity)
+            }
+            None => capacity,
+        };
+        let granted = (tokens.floor() as usize).min(wanted);
+        sqlx::query!(
+            "UPDATE send_rate_limit SET tokens = $1, refilled_at = $2",
+            tokens - granted as f64,
+            bucket.now
+        )
+        .execute(&mut *transaction)
+        .await?;
+        transaction.commit().await?;
+
+        if granted == 0 {
+            Ok(Permit::Wait(Duration::from_secs_f64((1.0 - tokens) / rate)))
+        } else {
+            Ok(Permit::Granted(granted))
+        }
+    }
+
+    /// Pauses every worker after the provider reported that we send too fast, twice as long as
+    /// the previous time. Workers that hit the limit during an ongoing pause do not extend it.
+    #[tracing::instrument(skip_all)]
+    pub async fn back_off(&self, pool: &PgPool) -> Result<(), Error> {
+        let backoff = sqlx::query!(
+            r#"UPDATE send_rate_limit
+            SET backoff_seconds = LEAST(GREATEST(backoff_seconds * 2, $1), $2),
+                paused_until = now() + make_interval(secs => LEAST(GREATEST(backoff_seconds * 2, $1), $2)),
+                refilled_at = now() + make_interval(secs => LEAST(GREATEST(backoff_seconds * 2, $1), $2)),
+                tokens = 0
+            WHERE paused_until IS NULL OR paused_until <= now()
+            RETURNING backoff_seconds"#,
+            INITIAL_BACKOFF_SECONDS,
+            MAX_BACKOFF_SECONDS
+        )
+        .fetch_optional(pool)
+        .await?;
+
+        if let Some(backoff) = backoff {
+            tracing::warn!(
+                backoff_seconds = backoff.backoff_seconds,
+                "The email provider asked us to slow down. Pausing deliveries."
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Forgets about previous pauses once sending succeeds again.
+    #[tracing::instrument(skip_all)]
+    pub async fn recover(&self, pool: &PgPool) -> Result<(), Error> {
+        sqlx::query!(
+            "UPDATE send_rate_limit SET backoff_seconds = 0
+            WHERE backoff_seconds > 0 AND paused_until <= now()"
+        )
+        .execute(pool)
+        .await?;
+
+        Ok(())
+    }
+}
@@ -1,6 +1,11 @@
-use actix_session::{storage::RedisSessionStore, SessionMiddleware};
+use actix_multipart::form::MultipartFormConfig;
+use actix_session::{
+    config::{PersistentSession, TtlExtensionPolicy},
+    storage::RedisSessionStore,
+    SessionMiddleware,
+};
 use actix_web::{
-    cookie::Key,
+    cookie::{self, Key},
     dev::Server,
     web::{self, Data},
     App, HttpServer,
@@ -9,14 +14,20 @@ use actix_web_flash_messages::{storage::CookieMessageStore, FlashMessagesFramewo
 use actix_web_lab::middleware;
 use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgPoolOptions, PgPool};
-use std::{io, net::TcpListener};
+use std::{
+    io,
+    net::{IpAddr, TcpListener},
+    sync::Arc,
+};
 use tracing_actix_web::TracingLogger;
 
 use crate::{
-    authentication,
-    configuration::{DatabaseSettings, Settings},
-    email_client::EmailClient,
+    attachments,
+    authentication::{self, MultipartCsrfLimit, PasswordHashing, PasswordPolicy},
+    configuration::{DatabaseSettings, SessionSettings, Settings},
+    email_client::EmailTransport,
     routes,
+    security_headers::{self, SecurityHeaders},
 };
 
 pub struct Application {
@@ -30,7 +41,7 @@ pub struct HmacSecret(pub Secret<String>);
 impl Application {
     pub async fn build(configuration: Settings) -> Result<Self, anyhow::Error> {
         let db_pool = get_db_pool(&configuration.database);
-        let email_client = configuration.email_client.client();
+        let email_client = configuration.email_client.client()?;
         let address = format!(
             "{}:{}",
             configuration.application.host, configuration.application.port
@@ -43,6 +54,11 @@ impl Application {
             email_client,
             configuration.application.base_url,
             configuration.application.hmac_secret,
+            configuration.application.trusted_proxies,
+            PasswordHashing::new(configuration.password_hashing.params()?)?,
+            configuration.password_policy.policy(),
+            configuration.session,
+            configuration.security_headers.headers()?,
             configuration.redis_uri,
         )
         .await?;
@@ -61,45 +77,117 @@ impl Application {
 
 pub struct ApplicationBaseUrl(pub String);
 
+pub struct TrustedProxies(pub Vec<IpAddr>);
+
 pub fn get_db_pool(config: &DatabaseSettings) -> PgPool {
     PgPoolOptions::new().connect_lazy_with(config.with_db())
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn run(
     listener: TcpListener,
     db_pool: PgPool,
-    email_client: EmailClient,
+    email_client: Arc<dyn EmailTransport>,
     base_url: String,
     hmac_secret: Secret<String>,
+    trusted_proxies: Vec<IpAddr>,
+    password_hashing: PasswordHashing,
+    password_policy: PasswordPolicy,
+    session_settings: SessionSettings,
+    security_headers: SecurityHeaders,
     redis_uri: Secret<String>,
 ) -> Result<Server, anyhow::Error> {
     let db_pool = Data::new(db_pool);
-    let email_client = Data::new(email_client);
+    let email_client = Data::from(email_client);
     let base_url = Data::new(ApplicationBaseUrl(base_url));
+    let trusted_proxies = Data::new(TrustedProxies(trusted_proxies));
+    let password_hashing = Data::new(password_hashing);
+    let password_policy = Data::new(password_policy);
+    let security_headers = Data::new(security_headers);
     let redis_store = RedisSessionStore::new(redis_uri.expose_secret()).await?;
 
     let secret_key = Key::from(hmac_secret.expose_secret().as_bytes());
     let message_store = CookieMessageStore::builder(secret_key.clone()).build();
     let message_framework = FlashMessagesFramework::builder(message_store).build();
 
+    let session_lifecycle = PersistentSession::default()
+        .session_ttl(cookie::time::Duration::try_from(
+            session_settings.idle_timeout(),
+        )?)
+        .session_ttl_extension_policy(TtlExtensionPolicy::OnEveryRequest);
+    let session_settings = Data::new(session_settings);
+
     let server = HttpServer::new(move || {
         App::new()
             .wrap(message_framework.clone())
-            .wrap(SessionMiddleware::new(
-                redis_store.clone(),
-                secret_key.clone(),
-            ))
+            .wrap(
+                SessionMiddleware::builder(redis_store.clone(), secret_key.clone())
+                    .cookie_name(session_settings.cookie_name.clone())
+                    .cookie_secure(session_settings.cookie_secure)
+                    .cookie_same_site(session_settings.cookie_same_site.into())
+                    .cookie_http_only(true)
+                    .session_lifecycle(session_lifecycle.clone())
+                    .build(),
+            )
+            .wrap(middleware::from_fn(security_headers::add_security_headers))
             .wrap(TracingLogger::default())
             .route("/", web::get().to(routes::home))
-            .route("/login", web::get().to(routes::login_form))
-            .route("/login", web::post().to(routes::login))
+            .route("/static/style.css", web::get().to(routes::stylesheet))
+            .service(
+                web::resource("/login")
+                    .wrap(middleware::from_fn(
+                        authentication::reject_invalid_csrf_tokens,
+                    ))
+                    .route(web::get().to(routes::login_form))
+                    .route(web::post().to(routes::login)),
+            )
             .route("/health_check", web::get().to(routes::health_check))
             .route("/subscriptions", web::post().to(routes::subscribe))
+            .route(
+                "/newsletters/{slug}/subscriptions",
+                web::post().to(routes::subscribe_to_newsletter),
+            )
             .route("/subscriptions/confirm", web::get().to(routes::confirm))
+            .route(
+                "/subscriptions/unsubscribe",
+                web::get().to(routes::unsubscribe),
+            )
+            .route("/preferences", web::get().to(routes::preferences))
+            .route("/preferences/name", web::post().to(routes::change_name))
+            .route("/preferences/email", web::post().to(routes::change_email))
+            .route("/preferences/tags", web::post().to(routes::change_tags))
+            .route("/preferences/pause", web::post().to(routes::pause_delivery))
+            .route(
+                "/preferences/unsubscribe",
+                web::post().to(routes::unsubscribe_from_newsletter),
+            )
+            .route("/t/o/{token}", web::get().to(routes::track_open))
+            .route("/t/c/{token}", web::get().to(routes::track_click))
             .service(
                 web::scope("/admin")
+                    .wrap(middleware::from_fn(
+                        authentication::reject_invalid_csrf_tokens,
+                    ))
                     .wrap(middleware::from_fn(authentication::reject_anonymous_users))
                     .route("/dashboard", web::get().to(routes::admin_dashboard))
+                    .service(
+                        web::resource("/attachments")
+                            .app_data(
+                                MultipartFormConfig::default()
+                                    .total_limit(attachments::MAX_UPLOAD_REQUEST_SIZE)
+                                    .memory_limit(attachments::MAX_UPLOAD_REQUEST_SIZE),
+                            )
+                            .route(web::get().to(routes::attachment_list))
+                            .route(web::post().to(routes::upload_attachment)),
+                    )
+                    .route(
+                        "/attachments/delete",
+                        web::post().to(routes::delete_attachment),
+                    )
+                    .route("/audit", web::get().to(routes::audit_log))
+                    .route("/engagement", web::get().to(routes::engagement_report))
+                    .route("/lists", web::get().to(routes::newsletter_lists))
+                    .route("/lists", web::post().to(routes::create_newsletter))
                     .route(
                         "/newsletters",
                         web::get().to(routes::publish_newsletter_form),
@@ -107,11 +195,39 @@ pub async fn run(
                     .route("/newsletters", web::post().to(routes::publish_newsletter))
                     .route("/password", web::get().to(routes::change_password_form))
                     .route("/password", web::post().to(routes::change_password))
+                    .route("/sessions", web::get().to(routes::active_sessions))
+                    .route("/sessions/revoke", web::post().to(routes::revoke_session))
+                    .route("/subscribers", web::get().to(routes::subscribers))
+                    .route(
+                        "/subscribers/tags",
+                        web::post().to(routes::update_subscriber_tags),
+                    )
+                    .route("/suppressions", web::get().to(routes::suppression_list))
+                    .route("/suppressions", web::post().to(routes::create_suppression))
+                    .route(
+                        "/suppressions/delete",
+                        web::post().to(routes::delete_suppression),
+                    )
+                    .route("/tokens", web::get().to(routes::api_tokens))
+                    .route("/tokens", web::post().to(routes::create_api_token))
+                    .route("/tokens/revoke", web::post().to(routes::revoke_api_token))
+                    .route(
+                        "/webhooks/postmark",
+                        web::post().to(routes::postmark_webhook),
+                    )
                     .route("/logout", web::post().to(routes::log_out)),
             )
             .app_data(db_pool.clone())
             .app_data(email_client.clone())
             .app_data(base_url.clone())
+            .app_data(trusted_proxies.clone())
+            .app_data(Data::new(MultipartCsrfLimit(
+                attachments::MAX_UPLOAD_REQUEST_SIZE,
+            )))
+            .app_data(password_hashing.clone())
+            .app_data(password_policy.clone())
+            .app_data(session_settings.clone())
+            .app_data(security_headers.clone())
             .app_data(Data::new(HmacSecret(hmac_secret.clone())))
     })
     .listen(listener)?
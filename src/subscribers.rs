@@ -0,0 +1,273 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{SubscriberEmail, SubscriberName, SubscriberTag};
+
+pub struct SubscriberSummary {
+    pub subscriber_id: Uuid,
+    pub newsletter: String,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub subscribed_at: DateTime<Utc>,
+    pub tags: Vec<String>,
+}
+
+/// A tag in use, with the number of confirmed subscribers an issue sent to it would reach.
+pub struct TagSummary {
+    pub tag: String,
+    pub confirmed_subscribers: i64,
+}
+
+/// Replaces the tags of a subscriber.
+#[tracing::instrument(name = "Set subscriber tags", skip(transaction))]
+pub async fn set_subscriber_tags(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    tags: &[SubscriberTag],
+) -> Result<(), Error> {
+    sqlx::query!(
+        "DELETE FROM subscriber_tags WHERE subscriber_id = $1",
+        subscriber_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to remove the tags of a subscriber.")?;
+
+    let tags = tags
+        .iter()
+        .map(|t| t.as_ref().to_owned())
+        .collect::<Vec<_>>();
+    sqlx::query!(
+        r#"INSERT INTO subscriber_tags (subscriber_id, tag)
+        SELECT $1, tag FROM UNNEST($2::TEXT[]) AS tag"#,
+        subscriber_id,
+        &tags
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to store the tags of a subscriber.")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Get subscriber email", skip(executor))]
+pub async fn get_subscriber_email(
+    subscriber_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<String>, Error> {
+    let row = sqlx::query!(
+        "SELECT email FROM subscriptions WHERE id = $1",
+        subscriber_id
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to retrieve a subscriber.")?;
+
+    Ok(row.map(|r| r.email))
+}
+
+/// What a subscriber can see and change on their preference page.
+pub struct SubscriberPreferences {
+    pub subscriber_id: Uuid,
+    pub newsletter_slug: String,
+    pub newsletter: String,
+    pub email: String,
+    pub name: String,
+    pub status: String,
+    pub paused_until: Option<DateTime<Utc>>,
+    pub tags: Vec<String>,
+}
+
+/// Looks up a subscriber by the token included in the links of every issue.
+#[tracing::instrument(name = "Get subscriber preferences", skip(token, executor))]
+pub async fn get_subscriber_preferences(
+    token: &str,
+    executor: impl PgExecutor<'_>,
+) -> Result<Option<SubscriberPreferences>, Error> {
+    let preferences = sqlx::query_as!(
+        SubscriberPreferences,
+        r#"SELECT
+            s.id AS subscriber_id,
+            n.slug AS newsletter_slug,
+            n.name AS newsletter,
+            s.email,
+            s.name,
+            s.status,
+            s.paused_until,
+            ARRAY(
+                SELECT tag FROM subscriber_tags WHERE subscriber_id = s.id ORDER BY tag
+            ) AS "tags!"
+        FROM subscriptions s
+        JOIN newsletters n ON n.newsletter_id = s.newsletter_id
+        WHERE s.unsubscribe_token = $1"#,
+        token
+    )
+    .fetch_optional(executor)
+    .await
+    .context("Failed to retrieve the preferences of a subscriber.")?;
+
+    Ok(preferences)
+}
+
+#[tracing::instrument(name = "Update subscriber name", skip(executor))]
+pub async fn update_subscriber_name(
+    subscriber_id: Uuid,
+    name: &SubscriberName,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET name = $2 WHERE id = $1",
+        subscriber_id,
+        name.as_ref()
+    )
+    .execute(executor)
+    .await
+    .context("Failed to update the name of a subscriber.")?;
+
+    Ok(())
+}
+
+/// Moves a subscriber to a new address, which has to be confirmed again before issues are
+/// delivered to it. Unsubscribed subscribers stay unsubscribed. Returns `false` if the address
+/// is already subscribed to the newsletter, or if the subscriber is suppressed.
+#[tracing::instrument(name = "Change subscriber email", skip(transaction))]
+pub async fn change_subscriber_email(
+    transaction: &mut Transaction<'_, Postgres>,
+    subscriber_id: Uuid,
+    email: &SubscriberEmail,
+) -> Result<bool, Error> {
+    let result = sqlx::query!(
+        r#"UPDATE subscriptions
+        SET email = $2,
+            status = CASE WHEN status = 'unsubscribed' THEN status ELSE 'pending_confirmation' END
+        WHERE id = $1 AND status <> 'suppressed'"#,
+        subscriber_id,
+        email.as_ref()
+    )
+    .execute(&mut **transaction)
+    .await;
+    let changed = match result {
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => return Ok(false),
+        result => result.context("Failed to change the email of a subscriber.")?,
+    };
+    if changed.rows_affected() == 0 {
+        return Ok(false);
+    }
+
+    // Confirmation links sent to the previous address must not confirm the new one.
+    sqlx::query!(
+        "DELETE FROM subscription_tokens WHERE subscriber_id = $1",
+        subscriber_id
+    )
+    .execute(&mut **transaction)
+    .await
+    .context("Failed to remove the confirmation tokens of a subscriber.")?;
+
+    Ok(true)
+}
+
+/// Stops delivery to a subscriber until the given time, or resumes it with `None`.
+#[tracing::instrument(name = "Pause subscriber", skip(executor))]
+pub async fn pause_subscriber(
+    subscriber_id: Uuid,
+    paused_until: Option<DateTime<Utc>>,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET paused_until = $2 WHERE id = $1",
+        subscriber_id,
+        paused_until
+    )
+    .execute(executor)
+    .await
+    .context("Failed to pause a subscriber.")?;
+
+    Ok(())
+}
+
+#[tracing::instrument(name = "Unsubscribe subscriber", skip(executor))]
+pub async fn unsubscribe_subscriber(
+    subscriber_id: Uuid,
+    executor: impl PgExecutor<'_>,
+) -> Result<(), Error> {
+    sqlx::query!(
+        "UPDATE subscriptions SET status = 'unsubscribed' WHERE id = $1",
+        subscriber_id
+    )
+    .execute(executor)
+    .await
+    .context("Failed to unsubscribe a subscriber.")?;
+
+    Ok(())
+}
+
+#[derive(Debug, Default)]
+pub struct SubscriberFilter<'a> {
+    pub newsletter: Option<&'a str>,
+    /// Matches the emails containing this text.
+    pub email: Option<&'a str>,
+}
+
+/// The most recent subscribers matching the filter, newest first.
+#[tracing::instrument(name = "Get subscribers", skip(pool))]
+pub async fn get_subscribers(
+    filter: &SubscriberFilter<'_>,
+    limit: i64,
+    pool: &PgPool,
+) -> Result<Vec<SubscriberSummary>, Error> {
+    let subscribers = sqlx::query_as!(
+        SubscriberSummary,
+        r#"SELECT
+            s.id AS subscriber_id,
+            n.name AS newsletter,
+            s.email,
+            s.name,
+            s.status,
+            s.subscribed_at,
+            COALESCE(
+                array_agg(t.tag ORDER BY t.tag) FILTER (WHERE t.tag IS NOT NULL),
+                '{}'
+            ) AS "tags!"
+        FROM subscriptions s
+        JOIN newsletters n ON n.newsletter_id = s.newsletter_id
+        LEFT JOIN subscriber_tags t ON t.subscriber_id = s.id
+        WHERE ($1::TEXT IS NULL OR n.slug = $1)
+            AND ($2::TEXT IS NULL OR strpos(lower(s.email), lower($2)) > 0)
+        GROUP BY s.id, n.name
+        ORDER BY s.subscribed_at DESC
+        LIMIT $3"#,
+        filter.newsletter,
+        filter.email,
+        limit
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the subscribers.")?;
+
+    Ok(subscribers)
+}
+
+/// The tags in use among the subscribers of a newsletter.
+#[tracing::instrument(name = "Get subscriber tags", skip(pool))]
+pub async fn get_tags(newsletter_id: Uuid, pool: &PgPool) -> Result<Vec<TagSummary>, Error> {
+    let tags = sqlx::query_as!(
+        TagSummary,
+        r#"SELECT
+            t.tag,
+            COUNT(*) FILTER (WHERE s.status = 'confirmed') AS "confirmed_subscribers!"
+        FROM subscriber_tags t
+        JOIN subscriptions s ON s.id = t.subscriber_id
+        WHERE s.newsletter_id = $1
+        GROUP BY t.tag
+        ORDER BY t.tag"#,
+        newsletter_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the subscriber tags.")?;
+
+    Ok(tags)
+}
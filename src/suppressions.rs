@@ -0,0 +1,154 @@
+use anyhow::{Context, Error};
+use chrono::{DateTime, Utc};
+use sqlx::{Executor, PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::domain::{SubscriberEmail, SuppressedAddress};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SuppressionSource {
+    /// Added by hand from the admin area.
+    Admin,
+    /// The email provider refused to deliver to the address.
+    Provider,
+    Bounce,
+    SpamComplaint,
+}
+
+impl SuppressionSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SuppressionSource::Admin => "admin",
+            SuppressionSource::Provider => "provider",
+            SuppressionSource::Bounce => "bounce",
+            SuppressionSource::SpamComplaint => "spam_complaint",
+        }
+    }
+}
+
+pub struct Suppression {
+    pub suppression_id: Uuid,
+    pub address: String,
+    pub reason: String,
+    pub source: String,
+    /// The username of the admin who added the entry.
+    pub created_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Adds the address to the suppression list and stops delivery to the confirmed subscribers it
+/// covers. Returns `None` if the address is already on the list.
+#[tracing::instrument(name = "Add a suppression", skip(transaction))]
+pub async fn add_suppression(
+    transaction: &mut Transaction<'_, Postgres>,
+    address: &SuppressedAddress,
+    reason: &str,
+    source: SuppressionSource,
+    created_by: Option<Uuid>,
+) -> Result<Option<Uuid>, Error> {
+    let suppression_id = sqlx::query!(
+        r#"INSERT INTO suppressions (
+            suppression_id, address, reason, source, created_by, created_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now())
+        ON CONFLICT (address) DO NOTHING
+        RETURNING suppression_id"#,
+        Uuid::new_v4(),
+        address.as_ref(),
+        reason,
+        source.as_str(),
+        created_by
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to add a suppression.")?
+    .map(|r| r.suppression_id);
+
+    if suppression_id.is_some() {
+        let query = sqlx::query!(
+            r#"UPDATE subscriptions SET status = 'suppressed'
+            WHERE status = 'confirmed'
+                AND $1 IN (lower(email), split_part(lower(email), '@', 2))"#,
+            address.as_ref()
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to suppress the subscribers covered by a suppression.")?;
+    }
+
+    Ok(suppression_id)
+}
+
+/// Removes an entry from the suppression list, returning its address, or `None` if there was
+/// no such entry. Subscribers it no longer covers receive issues again.
+#[tracing::instrument(name = "Remove a suppression", skip(transaction))]
+pub async fn remove_suppression(
+    transaction: &mut Transaction<'_, Postgres>,
+    suppression_id: Uuid,
+) -> Result<Option<String>, Error> {
+    let address = sqlx::query!(
+        r#"DELETE FROM suppressions WHERE suppression_id = $1 RETURNING address"#,
+        suppression_id
+    )
+    .fetch_optional(&mut **transaction)
+    .await
+    .context("Failed to remove a suppression.")?
+    .map(|r| r.address);
+
+    if address.is_some() {
+        let query = sqlx::query!(
+            r#"UPDATE subscriptions s SET status = 'confirmed'
+            WHERE status = 'suppressed'
+                AND NOT EXISTS (
+                    SELECT 1 FROM suppressions p
+                    WHERE p.address IN (lower(s.email), split_part(lower(s.email), '@', 2))
+                )"#
+        );
+        transaction
+            .execute(query)
+            .await
+            .context("Failed to restore the subscribers of a removed suppression.")?;
+    }
+
+    Ok(address)
+}
+
+/// The suppression list, most recent entries first.
+#[tracing::instrument(name = "Get suppressions", skip(pool))]
+pub async fn get_suppressions(pool: &PgPool) -> Result<Vec<Suppression>, Error> {
+    let suppressions = sqlx::query_as!(
+        Suppression,
+        r#"SELECT s.suppression_id, s.address, s.reason, s.source,
+            u.username AS "created_by?", s.created_at
+        FROM suppressions s
+        LEFT JOIN users u ON u.user_id = s.created_by
+        ORDER BY s.created_at DESC"#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the suppression list.")?;
+
+    Ok(suppressions)
+}
+
+/// Whether the email, or its domain, is on the suppression list.
+#[tracing::instrument(name = "Check the suppression list", skip(executor))]
+pub async fn is_suppressed(
+    email: &SubscriberEmail,
+    executor: impl PgExecutor<'_>,
+) -> Result<bool, Error> {
+    let suppressed = sqlx::query!(
+        r#"SELECT EXISTS (
+            SELECT 1 FROM suppressions
+            WHERE address IN (lower($1), split_part(lower($1), '@', 2))
+        ) AS "suppressed!""#,
+        email.as_ref()
+    )
+    .fetch_one(executor)
+    .await
+    .context("Failed to check the suppression list.")?
+    .suppressed;
+
+    Ok(suppressed)
+}
@@ -0,0 +1,258 @@
+//! Opt-in open and click tracking for newsletter issues. Links in the HTML body are rewritten
+//! to `/t/c/{token}`, which records the click and redirects to the original URL, and a pixel
+//! served from `/t/o/{token}` records opens. Tokens identify a recipient of an issue without
+//! revealing their email.
+
+use std::{
+    borrow::Cow,
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use anyhow::{Context, Error};
+use sqlx::{PgExecutor, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{html, utils};
+
+pub struct EngagementReport {
+    pub title: String,
+    pub newsletter: String,
+    pub published_at: String,
+    /// The subscribers the issue was sent to.
+    pub recipients: i64,
+    pub unique_opens: i64,
+    pub unique_clicks: i64,
+}
+
+/// The links of an HTML body that go through click tracking, in order of first appearance.
+/// Links to the application itself, such as the unsubscribe link, are left alone, and so are
+/// links built from merge fields.
+pub fn trackable_links(html: &str, base_url: &str) -> Vec<String> {
+    let links = Arc::new(Mutex::new(Vec::<String>::new()));
+    let base_url = base_url.to_owned();
+
+    let found = links.clone();
+    html::sanitiser()
+        .attribute_filter(move |element, attribute, value| {
+            let is_trackable = element == "a"
+                && attribute == "href"
+                && (value.starts_with("https://") || value.starts_with("http://"))
+                && !value.starts_with(&base_url)
+                && !value.contains("{{");
+            if is_trackable {
+                let mut found = found.lock().unwrap();
+                if !found.iter().any(|link| link == value) {
+                    found.push(value.to_owned());
+                }
+            }
+            Some(Cow::Borrowed(value))
+        })
+        .clean(html);
+
+    let links = links.lock().unwrap();
+    links.clone()
+}
+
+/// Points the trackable links of a recipient's HTML body at the click tracking redirect, and
+/// adds the open tracking pixel.
+pub fn add_tracking(html: &str, links: &[String], token: &str, base_url: &str) -> String {
+    let click_urls: HashMap<String, String> = links
+        .iter()
+        .enumerate()
+        .map(|(position, link)| {
+            let click_url = format!("{}/t/c/{}-{}", base_url, token, position);
+            (link.clone(), click_url)
+        })
+        .collect();
+
+    let tracked_html = html::sanitiser()
+        .attribute_filter(
+            move |element, attribute, value| match click_urls.get(value) {
+                Some(click_url) if element == "a" && attribute == "href" => {
+                    Some(Cow::Owned(click_url.clone()))
+                }
+                _ => Some(Cow::Borrowed(value)),
+            },
+        )
+        .clean(html)
+        .to_string();
+
+    format!(
+        r#"{}<img src="{}" width="1" height="1" alt="">"#,
+        tracked_html,
+        html::escape(&format!("{}/t/o/{}", base_url, token))
+    )
+}
+
+/// Stores the trackable links of an issue. Every worker finds the same links in an issue, so
+/// storing them again is harmless.
+#[tracing::instrument(name = "Store issue links", skip(executor, links))]
+pub async fn store_links(
+    newsletter_issue_id: Uuid,
+    links: &[String],
+    executor: impl PgExecutor<'_>,
+) -> Result<(), Error> {
+    let positions: Vec<i16> = (0..links.len() as i16).collect();
+    sqlx::query!(
+        r#"INSERT INTO issue_links (newsletter_issue_id, position, url)
+        SELECT $1, * FROM UNNEST($2::SMALLINT[], $3::TEXT[])
+        ON CONFLICT DO NOTHING"#,
+        newsletter_issue_id,
+        &positions,
+        links
+    )
+    .execute(executor)
+    .await
+    .context("Failed to store the links of an issue.")?;
+
+    Ok(())
+}
+
+/// The token of a subscriber for an issue. Retried deliveries keep the token they were first
+/// given.
+#[tracing::instrument(name = "Get tracking token", skip(transaction))]
+pub async fn tracking_token(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> Result<String, Error> {
+    let token = sqlx::query!(
+        r#"INSERT INTO issue_recipients (tracking_token, newsletter_issue_id, subscriber_id)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (newsletter_issue_id, subscriber_id)
+        DO UPDATE SET tracking_token = issue_recipients.tracking_token
+        RETURNING tracking_token"#,
+        utils::generate_token(),
+        newsletter_issue_id,
+        subscriber_id
+    )
+    .fetch_one(&mut **transaction)
+    .await
+    .context("Failed to store a tracking token.")?
+    .tracking_token;
+
+    Ok(token)
+}
+
+/// Records that the recipient opened the issue. Only the first open is kept.
+#[tracing::instrument(name = "Record an open", skip(executor))]
+pub async fn record_open(token: &str, executor: impl PgExecutor<'_>) -> Result<(), Error> {
+    sqlx::query!(
+        r#"UPDATE issue_recipients SET opened_at = now()
+        WHERE tracking_token = $1 AND opened_at IS NULL"#,
+        token
+    )
+    .execute(executor)
+    .await
+    .context("Failed to record an open.")?;
+
+    Ok(())
+}
+
+/// Records that the recipient clicked a link, returning the URL of the link, or `None` if
+/// there is no such link. A click also counts as an open, as the pixel is often blocked.
+#[tracing::instrument(name = "Record a click", skip(pool))]
+pub async fn record_click(
+    token: &str,
+    position: i16,
+    pool: &PgPool,
+) -> Result<Option<String>, Error> {
+    let url = sqlx::query!(
+        r#"SELECT l.url
+        FROM issue_recipients r
+        JOIN issue_links l ON l.newsletter_issue_id = r.newsletter_issue_id
+        WHERE r.tracking_token = $1 AND l.position = $2"#,
+        token,
+        position
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a tracked link.")?
+    .map(|r| r.url);
+
+    if url.is_some() {
+        sqlx::query!(
+            r#"UPDATE issue_recipients
+            SET clicked_at = COALESCE(clicked_at, now()), opened_at = COALESCE(opened_at, now())
+            WHERE tracking_token = $1"#,
+            token
+        )
+        .execute(pool)
+        .await
+        .context("Failed to record a click.")?;
+    }
+
+    Ok(url)
+}
+
+/// The unique opens and clicks of every issue published with tracking, newest first.
+#[tracing::instrument(name = "Get engagement report", skip(pool))]
+pub async fn get_engagement_report(pool: &PgPool) -> Result<Vec<EngagementReport>, Error> {
+    let report = sqlx::query_as!(
+        EngagementReport,
+        r#"SELECT i.title, n.name AS newsletter, i.published_at,
+            COUNT(r.tracking_token) AS "recipients!",
+            COUNT(r.opened_at) AS "unique_opens!",
+            COUNT(r.clicked_at) AS "unique_clicks!"
+        FROM newsletter_issues i
+        JOIN newsletters n ON n.newsletter_id = i.newsletter_id
+        LEFT JOIN issue_recipients r ON r.newsletter_issue_id = i.newsletter_issue_id
+        WHERE i.tracking_enabled
+        GROUP BY i.newsletter_issue_id, n.name
+        ORDER BY i.published_at DESC"#
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve the engagement report.")?;
+
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{add_tracking, trackable_links};
+
+    const BASE_URL: &str = "https://newsletter.example.com";
+
+    #[test]
+    fn external_links_are_trackable_once() {
+        let html = r#"<p><a href="https://blog.example.com/post">Read</a> or
+            <a href="https://blog.example.com/post">read again</a>
+            <a href="https://www.rust-lang.org">Rust</a></p>"#;
+
+        assert_eq!(
+            vec!["https://blog.example.com/post", "https://www.rust-lang.org"],
+            trackable_links(html, BASE_URL)
+        );
+    }
+
+    #[test]
+    fn own_links_and_merge_fields_are_not_trackable() {
+        let html = format!(
+            r#"<a href="{}/preferences">Preferences</a>
+            <a href="{{{{unsubscribe_url}}}}">Unsubscribe</a>
+            <a href="https://example.com/?email={{{{email}}}}">Profile</a>
+            <a href="mailto:editor@example.com">Write to us</a>"#,
+            BASE_URL
+        );
+
+        assert!(trackable_links(&html, BASE_URL).is_empty());
+    }
+
+    #[test]
+    fn tracked_links_point_to_the_redirect_and_a_pixel_is_added() {
+        let html = r#"<p><a href="https://www.rust-lang.org">Rust</a>
+            <a href="https://newsletter.example.com/preferences">Preferences</a></p>"#;
+        let links = trackable_links(html, BASE_URL);
+
+        let tracked = add_tracking(html, &links, "abc", BASE_URL);
+
+        assert!(tracked.contains(r#"href="https://newsletter.example.com/t/c/abc-0""#));
+        assert!(!tracked.contains("https://www.rust-lang.org"));
+        assert!(tracked.contains(r#"href="https://newsletter.example.com/preferences""#));
+        assert!(tracked.ends_with(
+            r#"<img src="https://newsletter.example.com/t/o/abc" width="1" height="1" alt="">"#
+        ));
+    }
+}
@@ -1,5 +1,8 @@
-use actix_web::{error, http::header::LOCATION, Error, HttpResponse};
-use std::fmt;
+use actix_web::{error, http::header::LOCATION, web::Data, Error, HttpRequest, HttpResponse};
+use rand::{distributions::Alphanumeric, Rng};
+use std::{fmt, iter};
+
+use crate::startup::TrustedProxies;
 
 pub fn e400<T>(e: T) -> Error
 where
@@ -8,6 +11,20 @@ where
     error::ErrorBadRequest(e)
 }
 
+pub fn e401<T>(e: T) -> Error
+where
+    T: fmt::Debug + fmt::Display + 'static,
+{
+    error::ErrorUnauthorized(e)
+}
+
+pub fn e404<T>(e: T) -> Error
+where
+    T: fmt::Debug + fmt::Display + 'static,
+{
+    error::ErrorNotFound(e)
+}
+
 pub fn e500<T>(e: T) -> Error
 where
     T: fmt::Debug + fmt::Display + 'static,
@@ -15,6 +32,37 @@ where
     error::ErrorInternalServerError(e)
 }
 
+/// The client's IP address. Forwarded headers can be set by anyone, so they are only read when
+/// the request comes from one of the trusted proxies.
+pub fn client_ip(request: &HttpRequest) -> Option<String> {
+    let peer_ip = request.peer_addr()?.ip();
+    let from_trusted_proxy = request
+        .app_data::<Data<TrustedProxies>>()
+        .is_some_and(|proxies| proxies.0.contains(&peer_ip));
+    if !from_trusted_proxy {
+        return Some(peer_ip.to_string());
+    }
+
+    request
+        .connection_info()
+        .realip_remote_addr()
+        .map(str::to_owned)
+}
+
+/// Fields left blank in a form are submitted as empty strings.
+pub fn non_empty(value: &Option<String>) -> Option<&str> {
+    value.as_deref().map(str::trim).filter(|v| !v.is_empty())
+}
+
+/// A random token for links sent to subscribers, such as confirmation or tracking links.
+pub fn generate_token() -> String {
+    let mut rng = rand::thread_rng();
+    iter::repeat_with(|| rng.sample(Alphanumeric))
+        .map(char::from)
+        .take(25)
+        .collect()
+}
+
 pub fn see_other(location: &str) -> HttpResponse {
     HttpResponse::SeeOther()
         .insert_header((LOCATION, location))
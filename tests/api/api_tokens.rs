@@ -0,0 +1,162 @@
+use reqwest::{Client, Response};
+use uuid::Uuid;
+
+use crate::helpers::{self, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_api_tokens() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_tokens().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn created_tokens_are_listed_with_their_scopes() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    create_api_token(&app, "Release notes CI").await;
+
+    let html_page = app.get_admin_tokens_html().await;
+    assert!(html_page.contains("Release notes CI"));
+    assert!(html_page.contains("newsletters:publish"));
+
+    let stored = sqlx::query!("SELECT token_hash FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved API token.");
+    assert!(!stored.token_hash.starts_with("z2p_"));
+}
+
+#[tokio::test]
+async fn new_tokens_are_only_shown_in_the_creation_response() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    let response = app
+        .post_create_api_token("name=CI&scopes=newsletters%3Apublish".into())
+        .await;
+    assert_eq!(200, response.status());
+    assert_eq!("no-store", response.headers()["Cache-Control"]);
+    for cookie in response.headers().get_all("Set-Cookie") {
+        assert!(!cookie.to_str().unwrap().contains("z2p_"));
+    }
+    assert!(response.text().await.unwrap().contains("z2p_"));
+
+    let html_page = app.get_admin_tokens_html().await;
+    assert!(html_page.contains("CI"));
+    assert!(!html_page.contains("z2p_"));
+}
+
+#[tokio::test]
+async fn a_token_must_have_at_least_one_scope() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+
+    let response = app.post_create_api_token("name=CI".into()).await;
+    helpers::assert_is_redirect_to(&response, "/admin/tokens");
+
+    let html_page = app.get_admin_tokens_html().await;
+    assert!(html_page.contains("<p><i>Select at least one scope for the token.</i></p>"));
+}
+
+#[tokio::test]
+async fn a_token_with_the_publish_scope_can_publish_a_newsletter() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    let token = create_api_token(&app, "CI").await;
+
+    let response = publish_newsletter_with_token(&app, &helpers::api_client(), &token).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let saved = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!("Newsletter title", saved.title);
+}
+
+#[tokio::test]
+async fn tokens_cannot_access_endpoints_outside_their_scopes() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    let token = create_api_token(&app, "CI").await;
+
+    let response = helpers::api_client()
+        .get(format!("{}/admin/dashboard", &app.address))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(403, response.status());
+}
+
+#[tokio::test]
+async fn invalid_tokens_are_rejected() {
+    let app = helpers::spawn_app().await;
+
+    let response =
+        publish_newsletter_with_token(&app, &helpers::api_client(), "z2p_not-a-real-token").await;
+
+    assert_eq!(401, response.status());
+}
+
+#[tokio::test]
+async fn revoked_tokens_are_rejected() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    let token = create_api_token(&app, "CI").await;
+
+    let token_id = sqlx::query!("SELECT token_id FROM api_tokens")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved API token.")
+        .token_id;
+    let response = app
+        .post_revoke_api_token(&serde_json::json!({ "token_id": token_id }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/tokens");
+
+    let response = publish_newsletter_with_token(&app, &helpers::api_client(), &token).await;
+    assert_eq!(401, response.status());
+}
+
+async fn create_api_token(app: &TestApp, name: &str) -> String {
+    let body = format!(
+        "name={}&scopes=newsletters%3Apublish",
+        urlencoding::encode(name)
+    );
+    let response = app.post_create_api_token(body).await;
+    assert_eq!(200, response.status());
+
+    let html_page = response.text().await.unwrap();
+    let start = html_page
+        .find("z2p_")
+        .expect("The new token was not shown.");
+    html_page[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+async fn publish_newsletter_with_token(app: &TestApp, client: &Client, token: &str) -> Response {
+    client
+        .post(format!("{}/admin/newsletters", &app.address))
+        .bearer_auth(token)
+        .form(&serde_json::json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
@@ -0,0 +1,396 @@
+use reqwest::multipart::{Form, Part};
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+const PNG: &[u8] = b"\x89PNG\r\n\x1a\nlogo";
+const PDF: &[u8] = b"%PDF-report";
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_attachments() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_attachments().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn uploaded_files_are_sent_with_the_issue() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+
+    let logo_id = upload(&app, "logo.png", "image/png", PNG, true).await;
+    let report_id = upload(&app, "report.pdf", "application/pdf", PDF, false).await;
+
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(html_page.contains("report.pdf has been uploaded."));
+    assert!(html_page.contains(&format!("cid:{}", logo_id)));
+    let audit_page = app.get_audit_log_html("action=upload_attachment").await;
+    assert!(audit_page.contains("<td>logo.png</td>"));
+    assert!(audit_page.contains("<td>report.pdf</td>"));
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let html_content = format!(r#"<p><img src="cid:{}" alt="Logo"></p>"#, logo_id);
+    let response = publish_newsletter(&app, &html_content, &[&logo_id, &report_id]).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let email = app.delivered_emails().await.pop().unwrap();
+    assert!(email["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!(r#"src="cid:{}""#, logo_id)));
+    assert_eq!(
+        json!([
+            {
+                "Name": "logo.png",
+                "Content": "iVBORw0KGgpsb2dv",
+                "ContentType": "image/png",
+                "ContentID": format!("cid:{}", logo_id)
+            },
+            {
+                "Name": "report.pdf",
+                "Content": "JVBERi1yZXBvcnQ=",
+                "ContentType": "application/pdf"
+            }
+        ]),
+        email["Attachments"]
+    );
+
+    // Sent files are no longer waiting for an issue.
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(!html_page.contains("<td>report.pdf</td>"));
+}
+
+#[tokio::test]
+async fn issues_without_attachments_are_sent_without_them() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+    upload(&app, "report.pdf", "application/pdf", PDF, false).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+    publish_newsletter(&app, "<p>Newsletter body as HTML</p>", &[]).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email = app.delivered_emails().await.pop().unwrap();
+    assert!(email.get("Attachments").is_none());
+}
+
+#[tokio::test]
+async fn disallowed_files_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = [
+        (
+            "setup.exe",
+            "application/x-msdownload",
+            false,
+            "application/x-msdownload files cannot be attached to an issue.",
+        ),
+        (
+            "logo.exe",
+            "image/png",
+            false,
+            "image/png files must be named with one of these extensions: png.",
+        ),
+        (
+            "report.pdf",
+            "application/pdf",
+            true,
+            "Only images can be shown inline.",
+        ),
+    ];
+    for (name, content_type, inline, error_message) in test_cases {
+        let response = app
+            .post_upload_attachment(upload_form(name, content_type, b"content", inline))
+            .await;
+        helpers::assert_is_redirect_to(&response, "/admin/attachments");
+
+        let html_page = app.get_admin_attachments_html().await;
+        assert!(
+            html_page.contains(error_message),
+            "The upload of {} was not rejected with the expected message.",
+            name
+        );
+    }
+
+    assert_eq!(0, count_attachments(&app).await);
+}
+
+#[tokio::test]
+async fn files_that_do_not_match_their_content_type_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_upload_attachment(upload_form(
+            "logo.png",
+            "image/png",
+            b"<html><script>alert(1)</script></html>",
+            true,
+        ))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(html_page.contains("logo.png is not a valid image/png file."));
+    assert_eq!(0, count_attachments(&app).await);
+}
+
+#[tokio::test]
+async fn files_over_the_size_limit_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let content = vec![0; 5 * 1024 * 1024 + 1];
+    let response = app
+        .post_upload_attachment(upload_form("large.pdf", "application/pdf", &content, false))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(html_page.contains("large.pdf is too large. Files cannot be larger than 5 MB."));
+    assert_eq!(0, count_attachments(&app).await);
+}
+
+#[tokio::test]
+async fn uploads_need_the_csrf_token_in_the_form_or_a_header() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let url = format!("{}/admin/attachments", &app.address);
+
+    let response = app
+        .api_client
+        .post(&url)
+        .multipart(upload_form("notes.txt", "text/plain", b"notes", false))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let form = upload_form("notes.txt", "text/plain", b"notes", false)
+        .text("csrf_token", app.csrf_token().await);
+    let response = app
+        .api_client
+        .post(&url)
+        .multipart(form)
+        .send()
+        .await
+        .unwrap();
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+    assert_eq!(1, count_attachments(&app).await);
+
+    let response = app
+        .api_client
+        .post(&url)
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .multipart(upload_form("notes.txt", "text/plain", b"notes", false))
+        .send()
+        .await
+        .unwrap();
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+    assert_eq!(2, count_attachments(&app).await);
+}
+
+#[tokio::test]
+async fn a_failed_request_only_retries_the_messages_it_left_unsent() {
+    let app = helpers::spawn_app().await;
+    for i in 0..6 {
+        subscribe_and_confirm(&app, &format!("ursula{}@example.com", i)).await;
+    }
+    app.test_user.login(&app).await;
+    // Messages with 7 MB of attachments are sent five at a time.
+    let large_id = upload(
+        &app,
+        "large.txt",
+        "text/plain",
+        &[b'a'; 5 * 1024 * 1024],
+        false,
+    )
+    .await;
+    let notes_id = upload(
+        &app,
+        "notes.txt",
+        "text/plain",
+        &[b'a'; 2 * 1024 * 1024],
+        false,
+    )
+    .await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .up_to_n_times(1)
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    helpers::when_sending_a_batch()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    publish_newsletter(
+        &app,
+        "<p>Newsletter body as HTML</p>",
+        &[&large_id, &notes_id],
+    )
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    let sent: Vec<_> = app.delivered_emails().await[..5]
+        .iter()
+        .map(|email| email["To"].as_str().unwrap().to_owned())
+        .collect();
+    let queued = sqlx::query!("SELECT subscriber_email, n_retries FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert!(!sent.contains(&queued[0].subscriber_email));
+    assert_eq!(1, queued[0].n_retries);
+}
+
+#[tokio::test]
+async fn files_cannot_be_sent_with_two_issues() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let report_id = upload(&app, "report.pdf", "application/pdf", PDF, false).await;
+    publish_newsletter(&app, "<p>First issue</p>", &[&report_id]).await;
+
+    let response = publish_newsletter(&app, "<p>Second issue</p>", &[&report_id]).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(
+        html_page.contains("Some of the attachments have been deleted or sent with another issue.")
+    );
+    let issues = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count issues.");
+    assert_eq!(1, issues.count);
+}
+
+#[tokio::test]
+async fn pending_files_can_be_deleted() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let report_id = upload(&app, "report.pdf", "application/pdf", PDF, false).await;
+
+    let response = app
+        .post_delete_attachment(&json!({ "attachment_id": report_id }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(html_page.contains("report.pdf has been deleted."));
+    assert_eq!(0, count_attachments(&app).await);
+
+    app.post_delete_attachment(&json!({ "attachment_id": report_id }))
+        .await;
+    let html_page = app.get_admin_attachments_html().await;
+    assert!(html_page.contains("The attachment does not exist or has already been sent."));
+}
+
+fn upload_form(name: &str, content_type: &str, content: &[u8], inline: bool) -> Form {
+    let file = Part::bytes(content.to_vec())
+        .file_name(name.to_owned())
+        .mime_str(content_type)
+        .unwrap();
+    let form = Form::new().part("file", file);
+
+    if inline {
+        form.text("inline", "true")
+    } else {
+        form
+    }
+}
+
+/// Uploads a file, returning its ID.
+async fn upload(
+    app: &TestApp,
+    name: &str,
+    content_type: &str,
+    content: &[u8],
+    inline: bool,
+) -> String {
+    let response = app
+        .post_upload_attachment(upload_form(name, content_type, content, inline))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/attachments");
+
+    sqlx::query!(
+        "SELECT attachment_id FROM attachments WHERE file_name = $1",
+        name
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("The file was not uploaded.")
+    .attachment_id
+    .to_string()
+}
+
+async fn count_attachments(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM attachments"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count attachments.")
+        .count
+}
+
+async fn publish_newsletter(
+    app: &TestApp,
+    html_content: &str,
+    attachment_ids: &[&str],
+) -> reqwest::Response {
+    let idempotency_key = Uuid::new_v4().to_string();
+    let mut body = vec![
+        ("title", "Newsletter title"),
+        ("text_content", "Newsletter body as plain text"),
+        ("html_content", html_content),
+        ("idempotency_key", &idempotency_key),
+    ];
+    body.extend(attachment_ids.iter().map(|id| ("attachments", *id)));
+
+    app.post_publish_newsletter(&body).await
+}
+
+async fn subscribe_and_confirm(app: &TestApp, email: &str) {
+    let _mock_guard = Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(email));
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
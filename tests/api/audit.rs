@@ -0,0 +1,160 @@
+use uuid::Uuid;
+
+use crate::helpers::{self, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_audit_log() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_audit_log("").await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn logging_in_and_out_is_audited() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    app.post_logout().await;
+
+    assert_eq!(vec!["log_in", "log_out"], recorded_actions(&app).await);
+}
+
+#[tokio::test]
+async fn forwarded_headers_from_untrusted_peers_are_ignored() {
+    let app = helpers::spawn_app().await;
+
+    app.api_client
+        .post(format!("{}/login", &app.address))
+        .header("X-CSRF-Token", app.csrf_token().await)
+        .header("X-Forwarded-For", "203.0.113.7")
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    let ip_address = sqlx::query!("SELECT ip_address FROM audit_log WHERE action = 'log_in'")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .ip_address;
+    assert_eq!(Some("127.0.0.1".to_owned()), ip_address);
+}
+
+#[tokio::test]
+async fn publishing_a_newsletter_is_audited_once() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let target = sqlx::query!(
+        "SELECT target FROM audit_log WHERE action = 'publish_newsletter' AND actor_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+    .target;
+    assert_eq!(Some(issue_id.to_string()), target);
+}
+
+#[tokio::test]
+async fn only_successful_password_changes_are_audited() {
+    let app = helpers::spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+    app.test_user.login(&app).await;
+
+    app.post_change_password(&serde_json::json!({
+        "current_password": Uuid::new_v4().to_string(),
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+    assert_eq!(vec!["log_in"], recorded_actions(&app).await);
+
+    app.post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+    assert_eq!(
+        vec!["log_in", "change_password"],
+        recorded_actions(&app).await
+    );
+}
+
+#[tokio::test]
+async fn the_audit_log_can_be_filtered_by_action() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let new_password = Uuid::new_v4().to_string();
+    app.post_change_password(&serde_json::json!({
+        "current_password": &app.test_user.password,
+        "new_password": &new_password,
+        "new_password_check": &new_password,
+    }))
+    .await;
+
+    let html_page = app.get_audit_log_html("action=change_password").await;
+    assert!(html_page.contains("<td>change_password</td>"));
+    assert!(!html_page.contains("<td>log_in</td>"));
+
+    let html_page = app.get_audit_log_html("action=&actor=").await;
+    assert!(html_page.contains("<td>change_password</td>"));
+    assert!(html_page.contains("<td>log_in</td>"));
+}
+
+#[tokio::test]
+async fn the_audit_log_can_be_filtered_by_actor() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let html_page = app
+        .get_audit_log_html(&format!("actor={}", app.test_user.username))
+        .await;
+    assert!(html_page.contains("<td>log_in</td>"));
+
+    let html_page = app.get_audit_log_html("actor=someone-else").await;
+    assert!(!html_page.contains("<td>log_in</td>"));
+}
+
+#[tokio::test]
+async fn filtering_by_an_unknown_action_is_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_audit_log("action=delete_everything").await;
+
+    assert_eq!(400, response.status());
+}
+
+async fn recorded_actions(app: &TestApp) -> Vec<String> {
+    sqlx::query!(
+        "SELECT action FROM audit_log WHERE actor_id = $1 ORDER BY occurred_at",
+        app.test_user.user_id
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap()
+    .into_iter()
+    .map(|r| r.action)
+    .collect()
+}
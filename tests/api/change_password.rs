@@ -117,3 +117,66 @@ async fn changing_password_works() {
         .await;
     helpers::assert_is_redirect_to(&response, "/admin/dashboard");
 }
+
+#[tokio::test]
+async fn new_password_must_be_long_enough() {
+    let app = helpers::spawn_app().await;
+    let new_password = "short";
+
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await;
+    assert!(
+        html_page.contains("<p><i>The new password must be at least 12 characters long.</i></p>")
+    );
+}
+
+#[tokio::test]
+async fn new_password_must_not_be_a_common_password() {
+    let app = helpers::spawn_app().await;
+    let new_password = "password1234";
+
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page
+        .contains("<p><i>The new password is too common, please choose a different one.</i></p>"));
+}
+
+#[tokio::test]
+async fn new_password_must_differ_from_the_current_one() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": &app.test_user.password,
+            "new_password_check": &app.test_user.password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/password");
+
+    let html_page = app.get_change_password_html().await;
+    assert!(html_page
+        .contains("<p><i>The new password must be different from the current one.</i></p>"));
+}
@@ -0,0 +1,97 @@
+use reqwest::Response;
+
+use crate::helpers::{self, TestApp};
+
+#[tokio::test]
+async fn login_without_a_csrf_token_is_rejected() {
+    let app = helpers::spawn_app().await;
+    app.get_login_html().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(403, response.status());
+}
+
+#[tokio::test]
+async fn the_csrf_token_can_be_submitted_as_a_form_field() {
+    let app = helpers::spawn_app().await;
+    let csrf_token = app.csrf_token().await;
+
+    let response = app
+        .api_client
+        .post(format!("{}/login", &app.address))
+        .form(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password,
+            "csrf_token": csrf_token
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    helpers::assert_is_redirect_to(&response, "/admin/dashboard");
+}
+
+#[tokio::test]
+async fn admin_actions_without_a_csrf_token_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = post_logout(&app, None).await;
+    assert_eq!(403, response.status());
+
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(200, response.status());
+}
+
+#[tokio::test]
+async fn a_csrf_token_from_another_session_is_rejected() {
+    let app = helpers::spawn_app().await;
+    let other_app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let other_csrf_token = other_app.csrf_token().await;
+    let response = post_logout(&app, Some(other_csrf_token)).await;
+
+    assert_eq!(403, response.status());
+}
+
+#[tokio::test]
+async fn admin_forms_include_the_csrf_token() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    let csrf_token_input = format!(r#"name="csrf_token" value="{}""#, app.csrf_token().await);
+
+    for html_page in [
+        app.get_admin_dashboard_html().await,
+        app.get_change_password_html().await,
+        app.get_publish_newsletter_html().await,
+        app.get_admin_sessions_html().await,
+        app.get_admin_tokens_html().await,
+    ] {
+        assert!(html_page.contains(&csrf_token_input));
+    }
+}
+
+async fn post_logout(app: &TestApp, csrf_token: Option<String>) -> Response {
+    let body = match csrf_token {
+        Some(csrf_token) => serde_json::json!({ "csrf_token": csrf_token }),
+        None => serde_json::json!({}),
+    };
+
+    app.api_client
+        .post(format!("{}/admin/logout", &app.address))
+        .form(&body)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
@@ -1,23 +1,27 @@
+use actix_web::cookie::{Cookie, CookieJar, Key};
+use actix_web_flash_messages::FlashMessage;
 use argon2::{password_hash::SaltString, Algorithm, Argon2, Params, PasswordHasher, Version};
 use linkify::{LinkFinder, LinkKind};
 use once_cell::sync::Lazy;
-use reqwest::{redirect::Policy, Client, Response, Url};
-use serde_json::Value;
+use reqwest::{cookie::Jar, multipart, redirect::Policy, Client, Response, Url};
+use secrecy::{ExposeSecret, Secret};
+use serde_json::{json, Value};
 use sqlx::{Connection, Executor, PgConnection, PgPool};
-use std::{env, io};
+use std::{env, io, sync::Arc};
 use uuid::Uuid;
-use wiremock::{MockServer, Request};
+use wiremock::{matchers, Mock, MockBuilder, MockServer, Request, Respond, ResponseTemplate};
 use zero2prod::{
-    configuration::{self, DatabaseSettings},
-    email_client::EmailClient,
+    configuration::{self, DatabaseSettings, EmailTransportKind},
+    email_client::EmailTransport,
     issue_delivery_worker::{self, ExecutionOutcome},
+    send_rate::SendRateLimiter,
     startup::{self, Application},
     telemetry,
 };
 
 static TRACING: Lazy<()> = Lazy::new(|| {
-    let subscriber_name = "test".into();
-    let env_filter = "info".into();
+    let subscriber_name = "test";
+    let env_filter = "info";
 
     if env::var("TEST_LOG").is_ok() {
         let subscriber = telemetry::get_subscriber(subscriber_name, env_filter, io::stdout);
@@ -56,16 +60,45 @@ impl TestUser {
         .await;
     }
 
-    async fn store(&self, pool: &PgPool) {
+    /// Logs in with a fresh client, as if from another browser, and returns that client.
+    pub async fn login_with_user_agent(&self, app: &TestApp, user_agent: &str) -> Client {
+        let client = api_client();
+
+        let login_html = client
+            .get(format!("{}/login", &app.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+            .text()
+            .await
+            .unwrap();
+
+        let response = client
+            .post(format!("{}/login", &app.address))
+            .header("User-Agent", user_agent)
+            .header("X-CSRF-Token", extract_csrf_token(&login_html))
+            .form(&serde_json::json!({
+                "username": &self.username,
+                "password": &self.password
+            }))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+        assert_is_redirect_to(&response, "/admin/dashboard");
+
+        client
+    }
+
+    pub fn password_hash(&self, params: Params) -> String {
         let salt = SaltString::generate(&mut rand::thread_rng());
-        let password_hash = Argon2::new(
-            Algorithm::Argon2id,
-            Version::V0x13,
-            Params::new(15000, 2, 1, None).unwrap(),
-        )
-        .hash_password(self.password.as_bytes(), &salt)
-        .unwrap()
-        .to_string();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password(self.password.as_bytes(), &salt)
+            .unwrap()
+            .to_string()
+    }
+
+    async fn store(&self, pool: &PgPool) {
+        let password_hash = self.password_hash(Params::new(15000, 2, 1, None).unwrap());
 
         sqlx::query!(
             "INSERT INTO users (user_id, username, password_hash) VALUES ($1, $2, $3)",
@@ -81,18 +114,35 @@ impl TestUser {
 
 pub struct TestApp {
     pub address: String,
+    pub base_url: String,
     pub port: u16,
     pub db_pool: PgPool,
     pub email_server: MockServer,
-    pub email_client: EmailClient,
+    pub email_client: Arc<dyn EmailTransport>,
+    pub rate_limiter: SendRateLimiter,
     pub test_user: TestUser,
     pub api_client: Client,
+    pub cookie_jar: Arc<Jar>,
+    pub hmac_secret: Secret<String>,
 }
 
 impl TestApp {
     pub async fn post_subscriptions(&self, body: String) -> Response {
         self.api_client
-            .post(&format!("{}/subscriptions", &self.address))
+            .post(format!("{}/subscriptions", &self.address))
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_newsletter_subscriptions(&self, newsletter: &str, body: String) -> Response {
+        self.api_client
+            .post(format!(
+                "{}/newsletters/{}/subscriptions",
+                &self.address, newsletter
+            ))
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(body)
             .send()
@@ -100,8 +150,55 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_preferences(&self, token: &str) -> Response {
+        self.api_client
+            .get(format!("{}/preferences", &self.address))
+            .query(&[("token", token)])
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_preferences_html(&self, token: &str) -> String {
+        self.get_preferences(token).await.text().await.unwrap()
+    }
+
+    /// Submits one of the forms of the preference page, e.g. `name` or `pause`.
+    pub async fn post_preferences<Body>(&self, form: &str, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/preferences/{}", &self.address, form))
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    /// Queues a flash message for the next page `api_client` loads, as if a handler had sent it.
+    pub fn add_flash_message(&self, content: &str) {
+        let messages = serde_json::to_string(&[FlashMessage::error(content)]).unwrap();
+        let key = Key::from(self.hmac_secret.expose_secret().as_bytes());
+
+        let mut jar = CookieJar::new();
+        jar.signed_mut(&key).add(Cookie::new("_flash", messages));
+        let signed_messages = jar.get("_flash").unwrap().value();
+
+        self.cookie_jar.add_cookie_str(
+            &format!("_flash={}", urlencoding::encode(signed_messages)),
+            &Url::parse(&self.address).unwrap(),
+        );
+    }
+
     pub fn get_confirmation_links(&self, email_request: &Request) -> ConfirmationLinks {
         let body: Value = serde_json::from_slice(&email_request.body).unwrap();
+
+        self.get_links(&body)
+    }
+
+    /// The link in an email, such as one returned by `delivered_emails`.
+    pub fn get_links(&self, body: &Value) -> ConfirmationLinks {
         let get_link = |s| {
             let links = LinkFinder::new()
                 .links(s)
@@ -120,8 +217,8 @@ impl TestApp {
             confirmation_link
         };
 
-        let html = get_link(&body["HtmlBody"].as_str().unwrap());
-        let plain_text = get_link(&body["TextBody"].as_str().unwrap());
+        let html = get_link(body["HtmlBody"].as_str().unwrap());
+        let plain_text = get_link(body["TextBody"].as_str().unwrap());
 
         ConfirmationLinks { html, plain_text }
     }
@@ -131,13 +228,19 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/login", &self.address))
+            .post(format!("{}/login", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    /// The CSRF token bound to the client's current session, as embedded in the login form.
+    pub async fn csrf_token(&self) -> String {
+        extract_csrf_token(&self.get_login_html().await)
+    }
+
     pub async fn get_login_html(&self) -> String {
         self.api_client
             .get(format!("{}/login", &self.address))
@@ -163,7 +266,7 @@ impl TestApp {
 
     pub async fn get_change_password(&self) -> Response {
         self.api_client
-            .get(&format!("{}/admin/password", &self.address))
+            .get(format!("{}/admin/password", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -174,7 +277,8 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/password", &self.address))
+            .post(format!("{}/admin/password", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
@@ -187,15 +291,238 @@ impl TestApp {
 
     pub async fn post_logout(&self) -> Response {
         self.api_client
-            .post(&format!("{}/admin/logout", &self.address))
+            .post(format!("{}/admin/logout", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_sessions(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/sessions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_sessions_html(&self) -> String {
+        self.get_admin_sessions().await.text().await.unwrap()
+    }
+
+    pub async fn post_revoke_session<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/sessions/revoke", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_lists(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/lists", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_lists_html(&self) -> String {
+        self.get_admin_lists().await.text().await.unwrap()
+    }
+
+    pub async fn post_create_newsletter<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/lists", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/subscribers?{}", &self.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_subscribers_html(&self, query: &str) -> String {
+        self.get_admin_subscribers(query)
+            .await
+            .text()
+            .await
+            .unwrap()
+    }
+
+    pub async fn post_subscriber_tags<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/subscribers/tags", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_attachments(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/attachments", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_attachments_html(&self) -> String {
+        self.get_admin_attachments().await.text().await.unwrap()
+    }
+
+    pub async fn post_upload_attachment(&self, form: multipart::Form) -> Response {
+        self.api_client
+            .post(format!("{}/admin/attachments", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .multipart(form)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_attachment<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/attachments/delete", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_engagement(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/engagement", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_engagement_html(&self) -> String {
+        self.get_admin_engagement().await.text().await.unwrap()
+    }
+
+    pub async fn get_admin_suppressions(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/suppressions", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_suppressions_html(&self) -> String {
+        self.get_admin_suppressions().await.text().await.unwrap()
+    }
+
+    pub async fn post_create_suppression<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/suppressions", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_delete_suppression<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/suppressions/delete", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_tokens(&self) -> Response {
+        self.api_client
+            .get(format!("{}/admin/tokens", &self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_admin_tokens_html(&self) -> String {
+        self.get_admin_tokens().await.text().await.unwrap()
+    }
+
+    pub async fn post_create_api_token(&self, body: String) -> Response {
+        self.api_client
+            .post(format!("{}/admin/tokens", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_revoke_api_token<Body>(&self, body: &Body) -> Response
+    where
+        Body: serde::Serialize,
+    {
+        self.api_client
+            .post(format!("{}/admin/tokens/revoke", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
+            .form(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn post_postmark_webhook(&self, token: &str, body: &Value) -> Response {
+        api_client()
+            .post(format!("{}/admin/webhooks/postmark", &self.address))
+            .bearer_auth(token)
+            .json(body)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_audit_log(&self, query: &str) -> Response {
+        self.api_client
+            .get(format!("{}/admin/audit?{}", &self.address, query))
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
+    pub async fn get_audit_log_html(&self, query: &str) -> String {
+        self.get_audit_log(query).await.text().await.unwrap()
+    }
+
     pub async fn get_publish_newsletter(&self) -> Response {
         self.api_client
-            .get(&format!("{}/admin/newsletters", &self.address))
+            .get(format!("{}/admin/newsletters", &self.address))
             .send()
             .await
             .expect("Failed to execute request.")
@@ -210,21 +537,40 @@ impl TestApp {
         Body: serde::Serialize,
     {
         self.api_client
-            .post(&format!("{}/admin/newsletters", &self.address))
+            .post(format!("{}/admin/newsletters", &self.address))
+            .header("X-CSRF-Token", self.csrf_token().await)
             .form(body)
             .send()
             .await
             .expect("Failed to execute request.")
     }
 
-    pub async fn dispatch_all_pending_emails(&self) {
+    /// The messages sent through Postmark's batch endpoint, in the order they were sent.
+    pub async fn delivered_emails(&self) -> Vec<Value> {
+        self.email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .filter(|request| request.url.path() == "/email/batch")
+            .flat_map(|request| serde_json::from_slice::<Vec<Value>>(&request.body).unwrap())
+            .collect()
+    }
+
+    /// Runs the delivery worker until the queue is empty or the send rate is used up.
+    pub async fn dispatch_all_pending_emails(&self) -> ExecutionOutcome {
         loop {
-            if let ExecutionOutcome::EmptyQueue =
-                issue_delivery_worker::try_execute_task(&self.db_pool, &self.email_client)
-                    .await
-                    .unwrap()
+            match issue_delivery_worker::try_execute_task(
+                &self.db_pool,
+                self.email_client.as_ref(),
+                &self.rate_limiter,
+                &self.base_url,
+            )
+            .await
+            .unwrap()
             {
-                break;
+                ExecutionOutcome::TaskCompleted => {}
+                outcome => return outcome,
             }
         }
     }
@@ -239,7 +585,9 @@ pub async fn spawn_app() -> TestApp {
         let mut c = configuration::get_configuration().expect("Failed to read configuration.");
         c.database.database_name = Uuid::new_v4().to_string();
         c.application.port = 0;
+        c.email_client.transport = EmailTransportKind::Postmark;
         c.email_client.base_url = email_server.uri();
+        c.delivery.messages_per_minute = None;
         c
     };
 
@@ -250,22 +598,27 @@ pub async fn spawn_app() -> TestApp {
         .expect("Failed to build application.");
     let port = app.port();
     let address = format!("http://127.0.0.1:{}", app.port());
-    let _ = tokio::spawn(app.run_until_stopped());
+    tokio::spawn(app.run_until_stopped());
 
+    let cookie_jar = Arc::new(Jar::default());
     let api_client = Client::builder()
         .redirect(Policy::none())
-        .cookie_store(true)
+        .cookie_provider(cookie_jar.clone())
         .build()
         .unwrap();
 
     let test_app = TestApp {
         address,
+        base_url: configuration.application.base_url.clone(),
         port,
         db_pool: startup::get_db_pool(&configuration.database),
         email_server,
-        email_client: configuration.email_client.client(),
+        email_client: configuration.email_client.client().unwrap(),
+        rate_limiter: configuration.delivery.rate_limiter(),
         test_user: TestUser::generate(),
         api_client,
+        cookie_jar,
+        hmac_secret: configuration.application.hmac_secret,
     };
 
     test_app.test_user.store(&test_app.db_pool).await;
@@ -273,6 +626,51 @@ pub async fn spawn_app() -> TestApp {
     test_app
 }
 
+pub fn when_sending_a_batch() -> MockBuilder {
+    Mock::given(matchers::path("/email/batch")).and(matchers::method("POST"))
+}
+
+/// Answers Postmark batch requests with a result for every message. The messages to the
+/// recipients in `rejections` fail with the given Postmark error code.
+#[derive(Default)]
+pub struct BatchResponder {
+    pub rejections: Vec<(String, i64)>,
+}
+
+impl Respond for BatchResponder {
+    fn respond(&self, request: &Request) -> ResponseTemplate {
+        let messages: Vec<Value> = serde_json::from_slice(&request.body).unwrap();
+        let results = messages
+            .iter()
+            .map(
+                |message| match self.rejections.iter().find(|(r, _)| message["To"] == *r) {
+                    Some((_, code)) => {
+                        json!({ "ErrorCode": code, "Message": "Rejected", "To": message["To"] })
+                    }
+                    None => json!({ "ErrorCode": 0, "Message": "OK", "To": message["To"] }),
+                },
+            )
+            .collect::<Vec<_>>();
+
+        ResponseTemplate::new(200).set_body_json(results)
+    }
+}
+
+pub fn api_client() -> Client {
+    Client::builder()
+        .redirect(Policy::none())
+        .cookie_store(true)
+        .build()
+        .unwrap()
+}
+
+pub fn extract_csrf_token(html: &str) -> String {
+    let (_, rest) = html
+        .split_once(r#"name="csrf_token" value=""#)
+        .expect("No CSRF token in the page.");
+    rest.split('"').next().unwrap().to_owned()
+}
+
 pub fn assert_is_redirect_to(response: &Response, location: &str) {
     assert_eq!(303, response.status());
     assert_eq!(location, response.headers().get("Location").unwrap());
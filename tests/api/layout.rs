@@ -0,0 +1,68 @@
+use crate::helpers;
+
+const STYLESHEET_LINK: &str = r#"<link rel="stylesheet" href="/static/style.css">"#;
+
+#[tokio::test]
+async fn the_shared_stylesheet_is_served() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/static/style.css", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status());
+    assert_eq!(
+        "text/css; charset=utf-8",
+        response.headers().get("Content-Type").unwrap()
+    );
+}
+
+#[tokio::test]
+async fn public_pages_use_the_shared_layout() {
+    let app = helpers::spawn_app().await;
+
+    let home_page = app
+        .api_client
+        .get(format!("{}/", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+        .text()
+        .await
+        .unwrap();
+    let login_page = app.get_login_html().await;
+
+    for html_page in [home_page, login_page] {
+        assert!(html_page.contains(STYLESHEET_LINK));
+        assert!(!html_page.contains("<nav>"));
+    }
+}
+
+#[tokio::test]
+async fn admin_pages_use_the_shared_layout_and_navigation() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    for html_page in [
+        app.get_admin_dashboard_html().await,
+        app.get_change_password_html().await,
+        app.get_publish_newsletter_html().await,
+        app.get_admin_sessions_html().await,
+        app.get_admin_tokens_html().await,
+        app.get_admin_subscribers_html("").await,
+        app.get_admin_lists_html().await,
+        app.get_audit_log_html("").await,
+        app.get_admin_suppressions_html().await,
+        app.get_admin_engagement_html().await,
+        app.get_admin_attachments_html().await,
+    ] {
+        assert!(html_page.contains(STYLESHEET_LINK));
+        assert!(html_page.contains(r#"<a href="/admin/dashboard">Dashboard</a>"#));
+        assert!(
+            html_page.contains(r#"<form name="logoutForm" action="/admin/logout" method="post">"#)
+        );
+    }
+}
@@ -0,0 +1,207 @@
+use serde_json::{json, Value};
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_newsletters() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_lists().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn admins_can_create_a_newsletter() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_create_newsletter(&json!({
+            "name": "Product updates",
+            "slug": "product-updates",
+            "sender_email": "updates@example.com"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/lists");
+
+    let html_page = app.get_admin_lists_html().await;
+    assert!(html_page.contains("The newsletter has been created."));
+    assert!(html_page.contains("<td>Product updates</td>"));
+    assert!(html_page.contains("<td>updates@example.com</td>"));
+
+    let audit_page = app.get_audit_log_html("action=create_newsletter").await;
+    assert!(audit_page.contains("<td>create_newsletter</td>"));
+}
+
+#[tokio::test]
+async fn invalid_newsletters_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = vec![
+        (
+            json!({ "name": "", "slug": "product-updates" }),
+            "The newsletter name must be between 1 and 100 characters long.",
+        ),
+        (
+            json!({ "name": "Product updates", "slug": "Product Updates" }),
+            "Product Updates is not a valid newsletter slug.",
+        ),
+        (
+            json!({ "name": "Product updates", "slug": "product-updates", "sender_email": "nope" }),
+            "nope is not a valid sender address.",
+        ),
+        (
+            json!({ "name": "Another default", "slug": "default" }),
+            "There is already a newsletter called default.",
+        ),
+    ];
+
+    for (body, error_message) in test_cases {
+        let response = app.post_create_newsletter(&body).await;
+        helpers::assert_is_redirect_to(&response, "/admin/lists");
+
+        let html_page = app.get_admin_lists_html().await;
+        assert!(
+            html_page.contains(error_message),
+            "The page did not show the error for {}.",
+            body
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_newsletter_returns_a_404() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .post_newsletter_subscriptions(
+            "missing",
+            "name=le%20guin&email=ursula_le_guin%40gmail.com".into(),
+        )
+        .await;
+
+    assert_eq!(404, response.status());
+}
+
+#[tokio::test]
+async fn the_same_email_can_subscribe_to_several_newsletters() {
+    let app = helpers::spawn_app().await;
+    create_newsletter(&app, "product-updates", Some("updates@example.com")).await;
+    mount_email_server(&app).await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    app.post_newsletter_subscriptions("product-updates", body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let subscriptions = sqlx::query!("SELECT COUNT(*) AS count FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(2), subscriptions.count);
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let email: Value = serde_json::from_slice(&email_request.body).unwrap();
+    assert_eq!("updates@example.com", email["From"]);
+    assert!(email["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Welcome to Product updates!"));
+}
+
+#[tokio::test]
+async fn issues_are_only_delivered_to_the_subscribers_of_their_newsletter() {
+    let app = helpers::spawn_app().await;
+    create_newsletter(&app, "product-updates", Some("updates@example.com")).await;
+    mount_email_server(&app).await;
+
+    subscribe_and_confirm(&app, None, "ursula_le_guin@gmail.com").await;
+    subscribe_and_confirm(&app, Some("product-updates"), "octavia_butler@gmail.com").await;
+
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "newsletter": "product-updates",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters?newsletter=product-updates");
+
+    app.dispatch_all_pending_emails().await;
+    let delivered_emails = app.delivered_emails().await;
+    assert_eq!(1, delivered_emails.len());
+
+    let email = &delivered_emails[0];
+    assert_eq!("octavia_butler@gmail.com", email["To"]);
+    assert_eq!("updates@example.com", email["From"]);
+
+    let html_page = app
+        .get_admin_subscribers_html("newsletter=product-updates")
+        .await;
+    assert!(html_page.contains("octavia_butler@gmail.com"));
+    assert!(!html_page.contains("ursula_le_guin@gmail.com"));
+}
+
+async fn create_newsletter(app: &TestApp, slug: &str, sender_email: Option<&str>) {
+    app.test_user.login(app).await;
+    let response = app
+        .post_create_newsletter(&json!({
+            "name": "Product updates",
+            "slug": slug,
+            "sender_email": sender_email.unwrap_or_default()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/lists");
+}
+
+async fn mount_email_server(app: &TestApp) {
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn subscribe_and_confirm(app: &TestApp, newsletter: Option<&str>, email: &str) {
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(email));
+    let response = match newsletter {
+        Some(newsletter) => app.post_newsletter_subscriptions(newsletter, body).await,
+        None => app.post_subscriptions(body).await,
+    };
+    response.error_for_status().unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
@@ -1,3 +1,5 @@
+use argon2::{Params, PasswordHash};
+
 use crate::helpers;
 
 #[tokio::test]
@@ -34,3 +36,44 @@ async fn redirect_to_admin_dashboard_after_login_success() {
     let html_page = app.get_admin_dashboard_html().await;
     assert!(html_page.contains(&format!("Welcome {}", app.test_user.username)));
 }
+
+#[tokio::test]
+async fn login_upgrades_password_hashes_with_weaker_parameters() {
+    let app = helpers::spawn_app().await;
+
+    let weak_password_hash = app
+        .test_user
+        .password_hash(Params::new(4096, 1, 1, None).unwrap());
+    sqlx::query!(
+        "UPDATE users SET password_hash = $1 WHERE user_id = $2",
+        weak_password_hash,
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    app.test_user.login(&app).await;
+
+    let saved = sqlx::query!(
+        "SELECT password_hash FROM users WHERE user_id = $1",
+        app.test_user.user_id
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved password hash.");
+    let saved_password_hash = PasswordHash::new(&saved.password_hash).unwrap();
+    let saved_params = Params::try_from(&saved_password_hash).unwrap();
+
+    assert_ne!(weak_password_hash, saved.password_hash);
+    assert_eq!(15000, saved_params.m_cost());
+    assert_eq!(2, saved_params.t_cost());
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/dashboard");
+}
@@ -1,8 +1,23 @@
 mod admin_dashboard;
+mod api_tokens;
+mod attachments;
+mod audit;
 mod change_password;
+mod csrf;
 mod health_check;
 mod helpers;
+mod layout;
+mod lists;
 mod login;
 mod newsletter;
+mod preferences;
+mod security_headers;
+mod sessions;
+mod subscribers;
 mod subscriptions;
 mod subscriptions_confirm;
+mod subscriptions_unsubscribe;
+mod suppressions;
+mod tracking;
+mod webhooks;
+mod xss;
@@ -3,11 +3,11 @@ use fake::{
     Fake,
 };
 use serde_json::json;
-use std::time::Duration;
 use uuid::Uuid;
 use wiremock::{matchers, Mock, MockBuilder, ResponseTemplate};
+use zero2prod::{issue_delivery_worker::ExecutionOutcome, send_rate::SendRateLimiter};
 
-use crate::helpers::{self, ConfirmationLinks, TestApp};
+use crate::helpers::{self, BatchResponder, ConfirmationLinks, TestApp};
 
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
@@ -46,8 +46,8 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200))
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -101,8 +101,8 @@ async fn newsletter_creation_is_idempotent() {
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200))
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -139,8 +139,8 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     create_confirmed_subscriber(&app).await;
     app.test_user.login(&app).await;
 
-    when_sending_an_email()
-        .respond_with(ResponseTemplate::new(200).set_delay(Duration::from_secs(2)))
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
         .expect(1)
         .mount(&app.email_server)
         .await;
@@ -164,15 +164,659 @@ async fn concurrent_form_submission_is_handled_gracefully() {
     app.dispatch_all_pending_emails().await;
 }
 
+#[tokio::test]
+async fn published_issues_record_their_author() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let saved = sqlx::query!("SELECT author_id, last_edited_by FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!(Some(app.test_user.user_id), saved.author_id);
+    assert_eq!(None, saved.last_edited_by);
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("<td>Newsletter title</td>"));
+    assert!(html_page.contains(&format!("<td>{}</td>", app.test_user.username)));
+}
+
+#[tokio::test]
+async fn replaying_a_publication_keeps_the_original_author() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let authors = sqlx::query!("SELECT author_id FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issues.");
+    assert_eq!(1, authors.len());
+    assert_eq!(Some(app.test_user.user_id), authors[0].author_id);
+}
+
+#[tokio::test]
+async fn markdown_content_generates_both_bodies() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "markdown_content": "Some *news* and a [link](https://example.com).",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let saved = sqlx::query!("SELECT text_content, html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!(
+        "Some news and a link (https://example.com).",
+        saved.text_content
+    );
+    assert!(saved.html_content.contains("<em>news</em>"));
+    assert!(saved
+        .html_content
+        .contains(r#"<a href="https://example.com" rel="noopener noreferrer">link</a>"#));
+}
+
+#[tokio::test]
+async fn explicit_content_overrides_the_bodies_generated_from_markdown() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "markdown_content": "Some *news*.",
+        "text_content": "Custom plain text",
+        "html_content": "",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let saved = sqlx::query!("SELECT text_content, html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!("Custom plain text", saved.text_content);
+    assert_eq!("<p>Some <em>news</em>.</p>\n", saved.html_content);
+}
+
+#[tokio::test]
+async fn html_generated_from_markdown_is_sanitised() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "markdown_content": "Hello <script>alert('xss')</script>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let saved = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert!(!saved.html_content.contains("<script>"));
+}
+
+#[tokio::test]
+async fn issues_without_any_content_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Only plain text",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page
+        .contains("Write the issue in Markdown, or provide both its plain text and HTML content."));
+    let issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count newsletter issues.");
+    assert_eq!(Some(0), issues.count);
+}
+
+#[tokio::test]
+async fn invalid_issues_are_rejected_with_an_error_message() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = vec![
+        (
+            json!({
+                "title": "",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+            "The title of the issue cannot be empty.",
+        ),
+        (
+            json!({
+                "title": "a".repeat(257),
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<p>Newsletter body as HTML</p>",
+            }),
+            "The title of the issue cannot be longer than 256 characters.",
+        ),
+        (
+            json!({
+                "title": "Newsletter title",
+                "text_content": "Newsletter body as plain text",
+                "html_content": "<script>alert(\"xss\")</script>",
+            }),
+            "The HTML content of the issue cannot be empty.",
+        ),
+    ];
+
+    for (mut body, error_message) in test_cases {
+        body["idempotency_key"] = json!(Uuid::new_v4().to_string());
+        let response = app.post_publish_newsletter(&body).await;
+        helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+        let html_page = app.get_publish_newsletter_html().await;
+        assert!(
+            html_page.contains(error_message),
+            "The form did not show the error for {}.",
+            body
+        );
+    }
+
+    let issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count newsletter issues.");
+    assert_eq!(Some(0), issues.count);
+}
+
+#[tokio::test]
+async fn html_content_is_sanitised_before_it_is_stored() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": r#"<p onclick="steal()">Newsletter body as HTML</p><script>alert("xss")</script>"#,
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+
+    let saved = sqlx::query!("SELECT html_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!("<p>Newsletter body as HTML</p>", saved.html_content);
+}
+
+#[tokio::test]
+async fn issues_are_personalised_for_each_subscriber() {
+    let app = helpers::spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+
+    let body = "name=Tom%20%26%20Jerry%27s&email=tom%40example.com";
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    reqwest::get(app.get_confirmation_links(email_request).html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "News for {{name}}",
+        "text_content": "Hi {{ name }}, this was sent to {{email}}.",
+        "html_content": "<p>Hi {{name}}, this was sent to {{email}}.</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    app.post_publish_newsletter(&newsletter_request_body).await;
+    app.dispatch_all_pending_emails().await;
+
+    let body = app.delivered_emails().await.pop().unwrap();
+    assert_eq!("News for Tom & Jerry's", body["Subject"]);
+    assert_eq!(
+        "Hi Tom & Jerry's, this was sent to tom@example.com.",
+        body["TextBody"]
+    );
+    assert_eq!(
+        "<p>Hi Tom &amp; Jerry&#x27;s, this was sent to tom@example.com.</p>",
+        body["HtmlBody"]
+    );
+}
+
+#[tokio::test]
+async fn unknown_merge_fields_are_rejected_at_publish_time() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Hi {{first_name}}",
+        "html_content": "<p>Hi {{first_name}}</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("{{first_name}} is not a known merge field."));
+    let issues = sqlx::query!("SELECT COUNT(*) AS count FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count newsletter issues.");
+    assert_eq!(Some(0), issues.count);
+}
+
+#[tokio::test]
+async fn segmented_issues_are_only_delivered_to_matching_subscribers() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let tagged_email = "ursula_le_guin@gmail.com";
+    let body = format!(
+        "name=le%20guin&email={}&tags=events",
+        urlencoding::encode(tagged_email)
+    );
+    confirm(subscribe(&app, body).await).await;
+    app.test_user.login(&app).await;
+
+    let newsletter_request_body = [
+        ("title", "Newsletter title"),
+        ("text_content", "Newsletter body as plain text"),
+        ("html_content", "<p>Newsletter body as HTML</p>"),
+        ("segments", "events"),
+        ("idempotency_key", &Uuid::new_v4().to_string()),
+    ];
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert_eq!(tagged_email, queued[0].subscriber_email);
+
+    let saved = sqlx::query!("SELECT segments FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved newsletter issue.");
+    assert_eq!(vec!["events"], saved.segments);
+    let html_page = app.get_publish_newsletter_html().await;
+    assert!(html_page.contains("events (1 confirmed)"));
+}
+
+#[tokio::test]
+async fn issues_are_delivered_in_batches() {
+    let app = helpers::spawn_app().await;
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(3, app.delivered_emails().await.len());
+    assert_eq!(Some(0), queued_deliveries(&app).await);
+}
+
+#[tokio::test]
+async fn messages_that_fail_temporarily_in_a_batch_are_retried_later() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let failing_email = "ursula_le_guin@gmail.com";
+    let body = format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(failing_email)
+    );
+    confirm(subscribe(&app, body).await).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder {
+            // Postmark is down for maintenance.
+            rejections: vec![(failing_email.into(), 100)],
+        })
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(2, app.delivered_emails().await.len());
+    let queued = sqlx::query!(
+        "SELECT subscriber_email, n_retries, execute_after > now() AS \"delayed!\"
+        FROM issue_delivery_queue"
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert_eq!(failing_email, queued[0].subscriber_email);
+    assert_eq!(1, queued[0].n_retries);
+    assert!(queued[0].delayed);
+
+    // Once the delay is over, only the failed message is sent again.
+    sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    let delivered_emails = app.delivered_emails().await;
+    assert_eq!(3, delivered_emails.len());
+    assert_eq!(failing_email, delivered_emails[2]["To"]);
+}
+
+#[tokio::test]
+async fn inactive_recipients_are_suppressed() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let inactive_email = "ursula_le_guin@gmail.com";
+    let body = format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(inactive_email)
+    );
+    confirm(subscribe(&app, body).await).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder {
+            rejections: vec![(inactive_email.into(), 406)],
+        })
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    // The delivery is dropped instead of being retried.
+    assert_eq!(Some(0), queued_deliveries(&app).await);
+    let subscriber = sqlx::query!(
+        "SELECT status FROM subscriptions WHERE email = $1",
+        inactive_email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the subscriber.");
+    assert_eq!("suppressed", subscriber.status);
+
+    // Later issues skip the suppressed address.
+    publish_newsletter(&app).await;
+    assert_eq!(Some(1), queued_deliveries(&app).await);
+}
+
+#[tokio::test]
+async fn rejected_messages_are_dropped_without_suppressing_the_recipient() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let rejected_email = "ursula_le_guin@gmail.com";
+    let body = format!(
+        "name=le%20guin&email={}",
+        urlencoding::encode(rejected_email)
+    );
+    confirm(subscribe(&app, body).await).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder {
+            // An invalid request that does not name the recipient.
+            rejections: vec![(rejected_email.into(), 300)],
+        })
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(Some(0), queued_deliveries(&app).await);
+    let subscriber = sqlx::query!(
+        "SELECT status FROM subscriptions WHERE email = $1",
+        rejected_email
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the subscriber.");
+    assert_eq!("confirmed", subscriber.status);
+    let suppressions = sqlx::query!("SELECT COUNT(*) AS \"count!\" FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count suppressions.");
+    assert_eq!(0, suppressions.count);
+}
+
+#[tokio::test]
+async fn deliveries_are_kept_when_the_provider_rejects_our_credentials() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(ResponseTemplate::new(401).set_body_json(json!({
+            "ErrorCode": 10,
+            "Message": "Bad or missing API token"
+        })))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert_eq!(1, queued[0].n_retries);
+    let suppressed = sqlx::query!(
+        "SELECT COUNT(*) AS \"count!\" FROM subscriptions WHERE status = 'suppressed'"
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to count suppressed subscribers.");
+    assert_eq!(0, suppressed.count);
+}
+
+#[tokio::test]
+async fn failed_batches_are_retried_later() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let retries = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(2, retries.len());
+    assert!(retries.iter().all(|r| r.n_retries == 1));
+}
+
+#[tokio::test]
+async fn deliveries_are_throttled_to_the_configured_rate() {
+    let mut app = helpers::spawn_app().await;
+    // One message per second, one at a time.
+    app.rate_limiter = SendRateLimiter::new(Some(60), 1);
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    let outcome = app.dispatch_all_pending_emails().await;
+
+    assert!(matches!(outcome, ExecutionOutcome::Throttled(_)));
+    assert_eq!(1, app.delivered_emails().await.len());
+    assert_eq!(Some(2), queued_deliveries(&app).await);
+}
+
+#[tokio::test]
+async fn idle_workers_can_send_a_burst_past_the_rate() {
+    let mut app = helpers::spawn_app().await;
+    app.rate_limiter = SendRateLimiter::new(Some(60), 3);
+    for _ in 0..3 {
+        create_confirmed_subscriber(&app).await;
+    }
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(3, app.delivered_emails().await.len());
+    assert_eq!(Some(0), queued_deliveries(&app).await);
+}
+
+#[tokio::test]
+async fn deliveries_pause_when_the_provider_rate_limits_us() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(ResponseTemplate::new(429))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    let outcome = app.dispatch_all_pending_emails().await;
+
+    // Every worker waits, and the delivery is kept without using up a retry.
+    assert!(matches!(outcome, ExecutionOutcome::Throttled(_)));
+    let pause = sqlx::query!(
+        r#"SELECT backoff_seconds, paused_until > now() AS "paused!" FROM send_rate_limit"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the send rate limit.");
+    assert!(pause.paused);
+    assert_eq!(5, pause.backoff_seconds);
+    let queued = sqlx::query!("SELECT n_retries FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert_eq!(0, queued[0].n_retries);
+}
+
+#[tokio::test]
+async fn deliveries_are_dropped_once_they_run_out_of_retries() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    publish_newsletter(&app).await;
+    sqlx::query!("UPDATE issue_delivery_queue SET n_retries = 5")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.dispatch_all_pending_emails().await;
+
+    assert_eq!(Some(0), queued_deliveries(&app).await);
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let newsletter_request_body = json!({
+        "title": "Newsletter title",
+        "text_content": "Newsletter body as plain text",
+        "html_content": "<p>Newsletter body as HTML</p>",
+        "idempotency_key": Uuid::new_v4().to_string()
+    });
+    let response = app.post_publish_newsletter(&newsletter_request_body).await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+}
+
+async fn queued_deliveries(app: &TestApp) -> Option<i64> {
+    sqlx::query!("SELECT COUNT(*) AS count FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count queued deliveries.")
+        .count
+}
+
 async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
     let name: String = Name().fake();
     let email: String = SafeEmail().fake();
-    let body = serde_urlencoded::to_string(&serde_json::json!({
+    let body = serde_urlencoded::to_string(serde_json::json!({
         "name": name,
         "email": email
     }))
     .unwrap();
 
+    subscribe(app, body).await
+}
+
+async fn subscribe(app: &TestApp, body: String) -> ConfirmationLinks {
     let _mock_guard = when_sending_an_email()
         .respond_with(ResponseTemplate::new(200))
         .named("Create unconfirmed subscriber")
@@ -180,7 +824,7 @@ async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
         .mount_as_scoped(&app.email_server)
         .await;
 
-    app.post_subscriptions(body.into())
+    app.post_subscriptions(body)
         .await
         .error_for_status()
         .unwrap();
@@ -193,12 +837,15 @@ async fn create_unconfirmed_subscriber(app: &TestApp) -> ConfirmationLinks {
         .pop()
         .unwrap();
 
-    app.get_confirmation_links(&email_request)
+    app.get_confirmation_links(email_request)
 }
 
 async fn create_confirmed_subscriber(app: &TestApp) {
     let confirmation_link = create_unconfirmed_subscriber(app).await;
+    confirm(confirmation_link).await;
+}
 
+async fn confirm(confirmation_link: ConfirmationLinks) {
     reqwest::get(confirmation_link.html)
         .await
         .unwrap()
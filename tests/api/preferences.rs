@@ -0,0 +1,412 @@
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+#[tokio::test]
+async fn unknown_preference_tokens_are_rejected_with_a_401() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_preferences("not-a-real-token").await;
+    assert_eq!(401, response.status());
+
+    let response = app
+        .post_preferences(
+            "name",
+            &json!({ "token": "not-a-real-token", "name": "Ursula" }),
+        )
+        .await;
+    assert_eq!(401, response.status());
+}
+
+#[tokio::test]
+async fn the_preferences_link_in_an_issue_opens_the_preference_page() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Manage your subscription at {{preferences_url}}",
+            "html_content": r#"<p><a href="{{preferences_url}}">Preferences</a></p>"#,
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+    app.dispatch_all_pending_emails().await;
+
+    let email = app.delivered_emails().await.pop().unwrap();
+    let preferences_links = app.get_links(&email);
+    assert_eq!("/preferences", preferences_links.html.path());
+    assert_eq!(preferences_links.html, preferences_links.plain_text);
+
+    let html_page = reqwest::get(preferences_links.html)
+        .await
+        .unwrap()
+        .text()
+        .await
+        .unwrap();
+    assert!(html_page.contains(r#"value="le guin""#));
+    assert!(html_page.contains("Issues are delivered to ursula_le_guin@gmail.com."));
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_name() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+
+    let response = app
+        .post_preferences(
+            "name",
+            &json!({ "token": token, "name": "Ursula K. Le Guin" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("<p><i>Your name has been updated.</i></p>"));
+    let saved = sqlx::query!("SELECT name FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("Ursula K. Le Guin", saved.name);
+}
+
+#[tokio::test]
+async fn invalid_names_and_emails_are_rejected_with_an_error_message() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+    let test_cases = vec![
+        (
+            "name",
+            json!({ "token": token, "name": "" }),
+            "is not a valid subscriber name.",
+        ),
+        (
+            "name",
+            json!({ "token": token, "name": "Ursula<" }),
+            "is not a valid subscriber name.",
+        ),
+        (
+            "email",
+            json!({ "token": token, "email": "definitely-not-an-email" }),
+            "is not a valid subscriber email.",
+        ),
+    ];
+
+    for (form, body, message) in test_cases {
+        let response = app.post_preferences(form, &body).await;
+        helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+        let html_page = app.get_preferences_html(&token).await;
+        assert!(
+            html_page.contains(message),
+            "The preference page did not explain why {} was rejected.",
+            body
+        );
+    }
+
+    let saved = sqlx::query!("SELECT name, email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("le guin", saved.name);
+    assert_eq!("ursula_le_guin@gmail.com", saved.email);
+    assert_eq!("confirmed", saved.status);
+}
+
+#[tokio::test]
+async fn changing_email_requires_confirming_the_new_address() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+
+    let response = app
+        .post_preferences(
+            "email",
+            &json!({ "token": token, "email": "ursula@example.com" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("ursula@example.com", saved.email);
+    assert_eq!("pending_confirmation", saved.status);
+
+    let email_requests = app.email_server.received_requests().await.unwrap();
+    let confirmation_email = email_requests.last().unwrap();
+    let body: serde_json::Value = serde_json::from_slice(&confirmation_email.body).unwrap();
+    assert_eq!("ursula@example.com", body["To"]);
+
+    // The link sent to the previous address no longer confirms the subscription.
+    let old_links = app.get_confirmation_links(&email_requests[0]);
+    let response = reqwest::get(old_links.html).await.unwrap();
+    assert_eq!(401, response.status());
+
+    let new_links = app.get_confirmation_links(confirmation_email);
+    reqwest::get(new_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("confirmed", saved.status);
+}
+
+#[tokio::test]
+async fn emails_already_subscribed_to_the_newsletter_cannot_be_taken() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+    app.post_subscriptions("name=someone&email=someone%40example.com".into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let response = app
+        .post_preferences(
+            "email",
+            &json!({ "token": token, "email": "someone@example.com" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("That email address cannot be used for this newsletter."));
+    let saved = sqlx::query!(
+        "SELECT status FROM subscriptions WHERE unsubscribe_token = $1",
+        token
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch saved subscription.");
+    assert_eq!("confirmed", saved.status);
+}
+
+#[tokio::test]
+async fn unsubscribed_subscribers_stay_unsubscribed_when_changing_email() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+    app.post_preferences("unsubscribe", &json!({ "token": token }))
+        .await;
+    let sent_emails = app.email_server.received_requests().await.unwrap().len();
+
+    let response = app
+        .post_preferences(
+            "email",
+            &json!({ "token": token, "email": "ursula@example.com" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("Your email address has been updated."));
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("ursula@example.com", saved.email);
+    assert_eq!("unsubscribed", saved.status);
+    assert_eq!(
+        sent_emails,
+        app.email_server.received_requests().await.unwrap().len()
+    );
+}
+
+#[tokio::test]
+async fn suppressed_subscribers_cannot_change_their_email() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+    sqlx::query!("UPDATE subscriptions SET status = 'suppressed'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app
+        .post_preferences(
+            "email",
+            &json!({ "token": token, "email": "ursula@example.com" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("Issues can no longer be delivered to this subscription."));
+    let saved = sqlx::query!("SELECT email, status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("ursula_le_guin@gmail.com", saved.email);
+    assert_eq!("suppressed", saved.status);
+}
+
+#[tokio::test]
+async fn subscribers_can_change_their_topics() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+
+    let response = app
+        .post_preferences(
+            "tags",
+            &json!({ "token": token, "tags": "Releases, events,releases" }),
+        )
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("Your topics have been updated."));
+    assert!(html_page.contains(r#"value="events, releases""#));
+
+    let response = app
+        .post_preferences("tags", &json!({ "token": token, "tags": "not a tag" }))
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("not a tag is not a valid tag."));
+    assert!(html_page.contains(r#"value="events, releases""#));
+}
+
+#[tokio::test]
+async fn paused_subscribers_do_not_receive_issues_until_they_resume() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_preferences("pause", &json!({ "token": token, "weeks": 4 }))
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+    assert!(app
+        .get_preferences_html(&token)
+        .await
+        .contains("Delivery is paused until"));
+
+    let sent_emails = app.email_server.received_requests().await.unwrap().len();
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(
+        sent_emails,
+        app.email_server.received_requests().await.unwrap().len()
+    );
+
+    app.post_preferences("pause", &json!({ "token": token, "weeks": 0 }))
+        .await;
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(
+        sent_emails + 1,
+        app.email_server.received_requests().await.unwrap().len()
+    );
+}
+
+#[tokio::test]
+async fn pauses_longer_than_a_year_are_rejected() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+
+    app.post_preferences("pause", &json!({ "token": token, "weeks": 53 }))
+        .await;
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("Delivery can be paused for up to 52 weeks."));
+    let saved = sqlx::query!("SELECT paused_until FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert!(saved.paused_until.is_none());
+}
+
+#[tokio::test]
+async fn subscribers_can_unsubscribe_from_the_preference_page() {
+    let app = helpers::spawn_app().await;
+    mock_email_server(&app).await;
+    create_confirmed_subscriber(&app).await;
+    let token = preferences_token(&app).await;
+
+    let response = app
+        .post_preferences("unsubscribe", &json!({ "token": token }))
+        .await;
+    helpers::assert_is_redirect_to(&response, &format!("/preferences?token={}", token));
+
+    let html_page = app.get_preferences_html(&token).await;
+    assert!(html_page.contains("You have been unsubscribed from Our newsletter."));
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("unsubscribed", saved.status);
+}
+
+async fn mock_email_server(app: &TestApp) {
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+}
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn preferences_token(app: &TestApp) -> String {
+    sqlx::query!("SELECT unsubscribe_token FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .unsubscribe_token
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+}
@@ -0,0 +1,91 @@
+use reqwest::Response;
+
+use crate::helpers;
+
+fn header<'a>(response: &'a Response, name: &str) -> &'a str {
+    response
+        .headers()
+        .get(name)
+        .unwrap_or_else(|| panic!("Missing {} header.", name))
+        .to_str()
+        .unwrap()
+}
+
+fn assert_restricted_headers(response: &Response) {
+    assert!(header(response, "Content-Security-Policy").contains("frame-ancestors 'none'"));
+    assert_eq!("DENY", header(response, "X-Frame-Options"));
+    assert_eq!("no-referrer", header(response, "Referrer-Policy"));
+    assert_eq!("nosniff", header(response, "X-Content-Type-Options"));
+}
+
+#[tokio::test]
+async fn the_login_page_has_restricted_security_headers() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/login", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_restricted_headers(&response);
+}
+
+#[tokio::test]
+async fn admin_pages_have_restricted_security_headers() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app.get_admin_dashboard().await;
+
+    assert_eq!(200, response.status());
+    assert_restricted_headers(&response);
+}
+
+#[tokio::test]
+async fn admin_redirects_for_anonymous_users_have_restricted_security_headers() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_dashboard().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+    assert_restricted_headers(&response);
+}
+
+#[tokio::test]
+async fn public_pages_have_the_default_security_headers() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(header(&response, "Content-Security-Policy").contains("default-src 'self'"));
+    assert_eq!("SAMEORIGIN", header(&response, "X-Frame-Options"));
+    assert_eq!(
+        "strict-origin-when-cross-origin",
+        header(&response, "Referrer-Policy")
+    );
+    assert_eq!("nosniff", header(&response, "X-Content-Type-Options"));
+}
+
+#[tokio::test]
+async fn hsts_is_not_sent_when_disabled() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .api_client
+        .get(format!("{}/login", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert!(response
+        .headers()
+        .get("Strict-Transport-Security")
+        .is_none());
+}
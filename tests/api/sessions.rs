@@ -0,0 +1,159 @@
+use reqwest::{Client, Response};
+use uuid::Uuid;
+
+use crate::helpers::{self, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_your_active_sessions() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_sessions().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn active_sessions_include_the_current_session() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+
+    let html_page = app.get_admin_sessions_html().await;
+    assert!(html_page.contains("(current session)"));
+}
+
+#[tokio::test]
+async fn active_sessions_include_sessions_on_other_devices() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    log_in_from_another_device(&app).await;
+
+    let html_page = app.get_admin_sessions_html().await;
+    assert!(html_page.contains("another-device"));
+}
+
+#[tokio::test]
+async fn revoking_a_session_logs_out_the_other_device() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+    let other_device = log_in_from_another_device(&app).await;
+    let session_id = get_other_device_session_id(&app).await;
+
+    let response = app
+        .post_revoke_session(&serde_json::json!({ "session_id": session_id }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/sessions");
+
+    let html_page = app.get_admin_sessions_html().await;
+    assert!(html_page.contains("<p><i>The session has been revoked.</i></p>"));
+    assert!(!html_page.contains("another-device"));
+
+    let response = get_admin_dashboard(&app, &other_device).await;
+    helpers::assert_is_redirect_to(&response, "/login");
+
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(200, response.status());
+}
+
+#[tokio::test]
+async fn changing_password_logs_out_other_sessions() {
+    let app = helpers::spawn_app().await;
+    let new_password = Uuid::new_v4().to_string();
+
+    app.test_user.login(&app).await;
+    let other_device = log_in_from_another_device(&app).await;
+
+    let response = app
+        .post_change_password(&serde_json::json!({
+            "current_password": &app.test_user.password,
+            "new_password": new_password,
+            "new_password_check": new_password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/password");
+
+    let response = get_admin_dashboard(&app, &other_device).await;
+    helpers::assert_is_redirect_to(&response, "/login");
+
+    let response = app.get_admin_dashboard().await;
+    assert_eq!(200, response.status());
+}
+
+#[tokio::test]
+async fn the_session_cookie_uses_the_configured_attributes() {
+    let app = helpers::spawn_app().await;
+
+    let response = app
+        .post_login(&serde_json::json!({
+            "username": &app.test_user.username,
+            "password": &app.test_user.password
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/dashboard");
+
+    let session_cookie = response
+        .headers()
+        .get_all("Set-Cookie")
+        .iter()
+        .map(|h| h.to_str().unwrap())
+        .find(|c| c.starts_with("session="))
+        .expect("No session cookie was set.");
+    assert!(session_cookie.contains("HttpOnly"));
+    assert!(session_cookie.contains("SameSite=Lax"));
+    assert!(session_cookie.contains("Max-Age=1800"));
+    assert!(!session_cookie.contains("Secure"));
+}
+
+#[tokio::test]
+async fn idle_sessions_expire() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+
+    sqlx::query!("UPDATE user_sessions SET last_active_at = now() - interval '1 hour'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_admin_dashboard().await;
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn sessions_expire_after_their_ttl_even_if_active() {
+    let app = helpers::spawn_app().await;
+
+    app.test_user.login(&app).await;
+
+    sqlx::query!("UPDATE user_sessions SET created_at = now() - interval '1 day'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    let response = app.get_admin_dashboard().await;
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+async fn log_in_from_another_device(app: &TestApp) -> Client {
+    app.test_user
+        .login_with_user_agent(app, "another-device")
+        .await
+}
+
+async fn get_other_device_session_id(app: &TestApp) -> Uuid {
+    sqlx::query!("SELECT session_id FROM user_sessions WHERE user_agent = 'another-device'")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the other device's session.")
+        .session_id
+}
+
+async fn get_admin_dashboard(app: &TestApp, client: &Client) -> Response {
+    client
+        .get(format!("{}/admin/dashboard", &app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
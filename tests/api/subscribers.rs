@@ -0,0 +1,109 @@
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_subscribers() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_subscribers("").await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn subscribers_can_be_searched_by_email() {
+    let app = helpers::spawn_app().await;
+    create_subscriber(&app, "ursula@example.com").await;
+    create_subscriber(&app, "octavia@example.com").await;
+    app.test_user.login(&app).await;
+
+    let html_page = app.get_admin_subscribers_html("email=URSULA").await;
+
+    assert!(html_page.contains("<td>ursula@example.com</td>"));
+    assert!(!html_page.contains("octavia@example.com"));
+}
+
+#[tokio::test]
+async fn admins_can_update_the_tags_of_a_subscriber() {
+    let app = helpers::spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "subscriber_id": subscriber_id,
+            "tags": "Events, product-updates"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/subscribers");
+
+    let html_page = app.get_admin_subscribers_html("").await;
+    assert!(html_page.contains("The tags of ursula@example.com have been updated."));
+    assert!(html_page.contains(r#"value="events, product-updates""#));
+
+    let audit_page = app
+        .get_audit_log_html("action=update_subscriber_tags")
+        .await;
+    assert!(audit_page.contains(&format!("<td>{}</td>", subscriber_id)));
+}
+
+#[tokio::test]
+async fn invalid_tags_are_rejected() {
+    let app = helpers::spawn_app().await;
+    let subscriber_id = create_subscriber(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "subscriber_id": subscriber_id,
+            "tags": "events, two words"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/subscribers");
+
+    let html_page = app.get_admin_subscribers_html("").await;
+    assert!(html_page.contains("two words is not a valid tag."));
+    let tags = sqlx::query!("SELECT COUNT(*) AS count FROM subscriber_tags")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(Some(0), tags.count);
+}
+
+#[tokio::test]
+async fn tags_of_unknown_subscribers_cannot_be_updated() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_subscriber_tags(&serde_json::json!({
+            "subscriber_id": uuid::Uuid::new_v4(),
+            "tags": "events"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/subscribers");
+
+    let html_page = app.get_admin_subscribers_html("").await;
+    assert!(html_page.contains("The subscriber does not exist."));
+}
+
+async fn create_subscriber(app: &TestApp, email: &str) -> uuid::Uuid {
+    let _mock_guard = Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(email));
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", email)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.")
+        .id
+}
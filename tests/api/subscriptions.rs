@@ -41,6 +41,33 @@ async fn subscribe_persists_the_new_subscriber() {
     assert_eq!("pending_confirmation", saved.status);
 }
 
+#[tokio::test]
+async fn subscribe_persists_the_selected_tags() {
+    let app = helpers::spawn_app().await;
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com&tags=News&tags=events&tags=news";
+
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let tags = sqlx::query!("SELECT tag FROM subscriber_tags ORDER BY tag")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved tags.")
+        .into_iter()
+        .map(|r| r.tag)
+        .collect::<Vec<_>>();
+
+    assert_eq!(vec!["events", "news"], tags);
+}
+
 #[tokio::test]
 async fn subscribe_returns_a_400_when_fields_are_present_but_invalid() {
     let app = helpers::spawn_app().await;
@@ -48,6 +75,10 @@ async fn subscribe_returns_a_400_when_fields_are_present_but_invalid() {
         ("name=&email=ursula_le_guin%40gmail.com", "empty name"),
         ("name=Ursula&email=", "empty email"),
         ("name=Ursula&email=definitely-not-an-email", "invalid email"),
+        (
+            "name=Ursula&email=ursula_le_guin%40gmail.com&tags=two%20words",
+            "invalid tag",
+        ),
     ];
 
     for (body, description) in test_cases {
@@ -112,7 +143,7 @@ async fn subscribe_sends_a_confirmation_email_with_a_link() {
     app.post_subscriptions(body.into()).await;
 
     let email_request = &app.email_server.received_requests().await.unwrap()[0];
-    let confirmation_links = app.get_confirmation_links(&email_request);
+    let confirmation_links = app.get_confirmation_links(email_request);
 
     assert_eq!(confirmation_links.html, confirmation_links.plain_text);
 }
@@ -0,0 +1,103 @@
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+#[tokio::test]
+async fn unsubscribing_without_token_is_rejected_with_a_400() {
+    let app = helpers::spawn_app().await;
+
+    let response = reqwest::get(&format!("{}/subscriptions/unsubscribe", app.address))
+        .await
+        .unwrap();
+
+    assert_eq!(400, response.status());
+}
+
+#[tokio::test]
+async fn unknown_unsubscribe_tokens_are_rejected_with_a_401() {
+    let app = helpers::spawn_app().await;
+
+    let response = reqwest::get(&format!(
+        "{}/subscriptions/unsubscribe?unsubscribe_token=not-a-real-token",
+        app.address
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(401, response.status());
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_in_an_issue_unsubscribes_the_subscriber() {
+    let app = helpers::spawn_app().await;
+    Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+    create_confirmed_subscriber(&app).await;
+    app.test_user.login(&app).await;
+
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+
+    let email = app.delivered_emails().await.pop().unwrap();
+    let unsubscribe_links = app.get_links(&email);
+    assert_eq!(
+        Some("/subscriptions/unsubscribe"),
+        Some(unsubscribe_links.html.path())
+    );
+    assert_eq!(unsubscribe_links.html, unsubscribe_links.plain_text);
+
+    let response = reqwest::get(unsubscribe_links.html).await.unwrap();
+    assert_eq!(200, response.status());
+
+    let saved = sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch saved subscription.");
+    assert_eq!("unsubscribed", saved.status);
+
+    // Later issues are no longer sent to them.
+    let sent_emails = app.email_server.received_requests().await.unwrap().len();
+    publish_newsletter(&app).await;
+    app.dispatch_all_pending_emails().await;
+    assert_eq!(
+        sent_emails,
+        app.email_server.received_requests().await.unwrap().len()
+    );
+}
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Unsubscribe at {{unsubscribe_url}}",
+            "html_content": r#"<p><a href="{{unsubscribe_url}}">Unsubscribe</a></p>"#,
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+}
@@ -0,0 +1,191 @@
+use serde_json::json;
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_suppression_list() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_suppressions().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+#[tokio::test]
+async fn suppressed_emails_do_not_receive_issues() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    subscribe_and_confirm(&app, "octavia@example.com").await;
+    app.test_user.login(&app).await;
+
+    suppress(&app, "Ursula@Example.com").await;
+
+    let html_page = app.get_admin_suppressions_html().await;
+    assert!(html_page.contains("Nothing will be sent to ursula@example.com anymore."));
+    assert!(html_page.contains("<td>ursula@example.com</td>"));
+    let audit_page = app.get_audit_log_html("action=create_suppression").await;
+    assert!(audit_page.contains("<td>ursula@example.com</td>"));
+
+    publish_newsletter(&app).await;
+    let queued = sqlx::query!("SELECT subscriber_email FROM issue_delivery_queue")
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch queued deliveries.");
+    assert_eq!(1, queued.len());
+    assert_eq!("octavia@example.com", queued[0].subscriber_email);
+}
+
+#[tokio::test]
+async fn queued_deliveries_to_newly_suppressed_emails_are_dropped() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+    publish_newsletter(&app).await;
+
+    suppress(&app, "ursula@example.com").await;
+
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    let queued = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count queued deliveries.");
+    assert_eq!(0, queued.count);
+}
+
+#[tokio::test]
+async fn suppressed_domains_cannot_subscribe() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    suppress(&app, "competitor.com").await;
+
+    Mock::given(matchers::any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_subscriptions("name=le%20guin&email=sales%40competitor.com".into())
+        .await;
+
+    // The response does not reveal that the address is suppressed.
+    assert_eq!(200, response.status().as_u16());
+    let saved = sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM subscriptions"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count subscriptions.");
+    assert_eq!(0, saved.count);
+}
+
+#[tokio::test]
+async fn invalid_suppressions_are_rejected() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let test_cases = [
+        (
+            json!({ "address": "not a domain", "reason": "Spam" }),
+            "not a domain is not a valid email address or domain.",
+        ),
+        (
+            json!({ "address": "competitor.com", "reason": " " }),
+            "The reason must be between 1 and 200 characters long.",
+        ),
+    ];
+    for (body, error_message) in test_cases {
+        let response = app.post_create_suppression(&body).await;
+        helpers::assert_is_redirect_to(&response, "/admin/suppressions");
+
+        let html_page = app.get_admin_suppressions_html().await;
+        assert!(html_page.contains(error_message));
+    }
+}
+
+#[tokio::test]
+async fn deleting_a_suppression_restores_its_subscribers() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+    suppress(&app, "example.com").await;
+    assert_eq!("suppressed", subscriber_status(&app).await);
+
+    let suppression_id = sqlx::query!("SELECT suppression_id FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the suppression.")
+        .suppression_id;
+    let response = app
+        .post_delete_suppression(&json!({ "suppression_id": suppression_id }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/suppressions");
+
+    let html_page = app.get_admin_suppressions_html().await;
+    assert!(html_page.contains("example.com is no longer suppressed."));
+    assert_eq!("confirmed", subscriber_status(&app).await);
+}
+
+async fn suppress(app: &TestApp, address: &str) {
+    let response = app
+        .post_create_suppression(&json!({
+            "address": address,
+            "reason": "Asked not to be contacted"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/suppressions");
+}
+
+async fn subscribe_and_confirm(app: &TestApp, email: &str) {
+    let _mock_guard = Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(email));
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .status
+}
+
+async fn publish_newsletter(app: &TestApp) {
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": "<p>Newsletter body as HTML</p>",
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+}
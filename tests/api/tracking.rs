@@ -0,0 +1,160 @@
+use linkify::{LinkFinder, LinkKind};
+use reqwest::Url;
+use serde_json::{json, Value};
+use uuid::Uuid;
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, BatchResponder, TestApp};
+
+const HTML_CONTENT: &str = r#"<p>Read <a href="https://www.rust-lang.org/learn">the book</a>.</p>"#;
+
+#[tokio::test]
+async fn issues_are_not_tracked_unless_requested() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    app.test_user.login(&app).await;
+
+    publish_newsletter(&app, false).await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_body = html_bodies(&app).await.pop().unwrap();
+    assert!(html_body.contains(r#"href="https://www.rust-lang.org/learn""#));
+    assert!(!html_body.contains("/t/"));
+}
+
+#[tokio::test]
+async fn tracked_issues_count_unique_opens_and_clicks() {
+    let app = helpers::spawn_app().await;
+    subscribe_and_confirm(&app, "ursula@example.com").await;
+    subscribe_and_confirm(&app, "octavia@example.com").await;
+    app.test_user.login(&app).await;
+
+    publish_newsletter(&app, true).await;
+    app.dispatch_all_pending_emails().await;
+
+    let html_bodies = html_bodies(&app).await;
+    assert_eq!(2, html_bodies.len());
+    for html_body in &html_bodies {
+        assert!(!html_body.contains("https://www.rust-lang.org/learn"));
+        assert!(!html_body.contains("example.com"));
+    }
+
+    // The first recipient opens the issue twice and clicks the link.
+    let client = helpers::api_client();
+    let pixel_url = tracking_link(&app, &html_bodies[0], "/t/o/");
+    for _ in 0..2 {
+        let response = client.get(pixel_url.clone()).send().await.unwrap();
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!("image/gif", response.headers()["Content-Type"]);
+    }
+    let click_url = tracking_link(&app, &html_bodies[0], "/t/c/");
+    let response = client.get(click_url).send().await.unwrap();
+    assert_eq!(302, response.status().as_u16());
+    assert_eq!(
+        "https://www.rust-lang.org/learn",
+        response.headers()["Location"]
+    );
+
+    let html_page = app.get_admin_engagement_html().await;
+    assert!(html_page.contains("<td>Newsletter title</td>"));
+    let report = sqlx::query!(
+        r#"SELECT COUNT(*) AS "recipients!", COUNT(opened_at) AS "opens!",
+            COUNT(clicked_at) AS "clicks!"
+        FROM issue_recipients"#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .expect("Failed to fetch the engagement of the issue.");
+    assert_eq!((2, 1, 1), (report.recipients, report.opens, report.clicks));
+}
+
+#[tokio::test]
+async fn unknown_click_tokens_are_rejected_with_a_404() {
+    let app = helpers::spawn_app().await;
+
+    for token in ["not-a-token", "no_position"] {
+        let response = helpers::api_client()
+            .get(format!("{}/t/c/{}", &app.address, token))
+            .send()
+            .await
+            .unwrap();
+
+        assert_eq!(404, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn you_must_be_logged_in_to_see_the_engagement_report() {
+    let app = helpers::spawn_app().await;
+
+    let response = app.get_admin_engagement().await;
+
+    helpers::assert_is_redirect_to(&response, "/login");
+}
+
+fn tracking_link(app: &TestApp, html_body: &str, path: &str) -> Url {
+    let link = LinkFinder::new()
+        .links(html_body)
+        .filter(|l| *l.kind() == LinkKind::Url && l.as_str().contains(path))
+        .map(|l| l.as_str().to_owned())
+        .next()
+        .expect("The email has no tracking link.");
+    let mut link = Url::parse(&link).unwrap();
+    link.set_port(Some(app.port)).unwrap();
+
+    link
+}
+
+async fn html_bodies(app: &TestApp) -> Vec<String> {
+    app.delivered_emails()
+        .await
+        .iter()
+        .map(|email: &Value| email["HtmlBody"].as_str().unwrap().to_owned())
+        .collect()
+}
+
+async fn subscribe_and_confirm(app: &TestApp, email: &str) {
+    let _mock_guard = Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let body = format!("name=le%20guin&email={}", urlencoding::encode(email));
+    app.post_subscriptions(body)
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn publish_newsletter(app: &TestApp, track_engagement: bool) {
+    helpers::when_sending_a_batch()
+        .respond_with(BatchResponder::default())
+        .mount(&app.email_server)
+        .await;
+
+    let response = app
+        .post_publish_newsletter(&json!({
+            "title": "Newsletter title",
+            "text_content": "Newsletter body as plain text",
+            "html_content": HTML_CONTENT,
+            "track_engagement": track_engagement,
+            "idempotency_key": Uuid::new_v4().to_string()
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/newsletters");
+}
@@ -0,0 +1,203 @@
+use serde_json::{json, Value};
+use wiremock::{matchers, Mock, ResponseTemplate};
+
+use crate::helpers::{self, TestApp};
+
+const EMAIL: &str = "ursula_le_guin@gmail.com";
+
+#[tokio::test]
+async fn hard_bounces_suppress_the_subscriber() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+    let response = app
+        .post_postmark_webhook(&token, &bounce(1, "HardBounce"))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("suppressed", subscriber_status(&app).await);
+    let event = sqlx::query!("SELECT kind, bounce_type, email FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the recorded event.");
+    assert_eq!("bounce", event.kind);
+    assert_eq!(Some("HardBounce".into()), event.bounce_type);
+    assert_eq!(EMAIL, event.email);
+    let suppression = sqlx::query!("SELECT address, source FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the suppression.");
+    assert_eq!(EMAIL, suppression.address);
+    assert_eq!("bounce", suppression.source);
+}
+
+#[tokio::test]
+async fn spam_complaints_suppress_the_subscriber() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+    let response = app
+        .post_postmark_webhook(
+            &token,
+            &json!({
+                "RecordType": "SpamComplaint",
+                "ID": 2,
+                "Type": "SpamComplaint",
+                "Email": EMAIL,
+                "Description": "The subscriber marked this message as spam."
+            }),
+        )
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("suppressed", subscriber_status(&app).await);
+}
+
+#[tokio::test]
+async fn undeliverable_bounce_types_suppress_the_subscriber() {
+    for (id, bounce_type) in [(6, "BadEmailAddress"), (7, "ManuallyDeactivated")] {
+        let app = helpers::spawn_app().await;
+        create_confirmed_subscriber(&app).await;
+        let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+        let response = app
+            .post_postmark_webhook(&token, &bounce(id, bounce_type))
+            .await;
+
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!("suppressed", subscriber_status(&app).await);
+    }
+}
+
+#[tokio::test]
+async fn other_webhooks_are_acknowledged_without_being_recorded() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+    let delivery = json!({
+        "RecordType": "Delivery",
+        "MessageID": "883953f4-6105-42a2-a16a-77a8eac79483",
+        "Recipient": EMAIL,
+        "DeliveredAt": "2026-10-19T16:33:54Z"
+    });
+    let response = app.post_postmark_webhook(&token, &delivery).await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(0, recorded_events(&app).await);
+    assert_eq!("confirmed", subscriber_status(&app).await);
+}
+
+#[tokio::test]
+async fn soft_bounces_are_recorded_without_suppressing_the_subscriber() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+    let response = app
+        .post_postmark_webhook(&token, &bounce(3, "SoftBounce"))
+        .await;
+
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!("confirmed", subscriber_status(&app).await);
+    assert_eq!(1, recorded_events(&app).await);
+}
+
+#[tokio::test]
+async fn repeated_webhooks_are_recorded_once() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "webhooks%3Apostmark").await;
+
+    for _ in 0..2 {
+        let response = app
+            .post_postmark_webhook(&token, &bounce(4, "HardBounce"))
+            .await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    assert_eq!(1, recorded_events(&app).await);
+}
+
+#[tokio::test]
+async fn webhooks_require_a_token_with_the_webhook_scope() {
+    let app = helpers::spawn_app().await;
+    create_confirmed_subscriber(&app).await;
+    let token = create_webhook_token(&app, "newsletters%3Apublish").await;
+
+    let response = app
+        .post_postmark_webhook(&token, &bounce(5, "HardBounce"))
+        .await;
+
+    assert_eq!(403, response.status().as_u16());
+    assert_eq!("confirmed", subscriber_status(&app).await);
+}
+
+fn bounce(id: i64, bounce_type: &str) -> Value {
+    json!({
+        "RecordType": "Bounce",
+        "ID": id,
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "Email": EMAIL,
+        "Description": "The server was unable to deliver your message.",
+        "Inactive": true
+    })
+}
+
+async fn create_confirmed_subscriber(app: &TestApp) {
+    let _mock_guard = Mock::given(matchers::path("/email"))
+        .and(matchers::method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let body = "name=le%20guin&email=ursula_le_guin%40gmail.com";
+    app.post_subscriptions(body.into())
+        .await
+        .error_for_status()
+        .unwrap();
+
+    let email_request = &app.email_server.received_requests().await.unwrap()[0];
+    let confirmation_links = app.get_confirmation_links(email_request);
+    reqwest::get(confirmation_links.html)
+        .await
+        .unwrap()
+        .error_for_status()
+        .unwrap();
+}
+
+async fn create_webhook_token(app: &TestApp, scope: &str) -> String {
+    app.test_user.login(app).await;
+    let response = app
+        .post_create_api_token(format!("name=Postmark&scopes={}", scope))
+        .await;
+    assert_eq!(200, response.status());
+
+    let html_page = response.text().await.unwrap();
+    let start = html_page
+        .find("z2p_")
+        .expect("The new token was not shown.");
+    html_page[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .collect()
+}
+
+async fn subscriber_status(app: &TestApp) -> String {
+    sqlx::query!("SELECT status FROM subscriptions WHERE email = $1", EMAIL)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to fetch the subscriber.")
+        .status
+}
+
+async fn recorded_events(app: &TestApp) -> i64 {
+    sqlx::query!(r#"SELECT COUNT(*) AS "count!" FROM email_events"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("Failed to count recorded events.")
+        .count
+}
@@ -0,0 +1,106 @@
+use crate::helpers;
+
+const PAYLOAD: &str = r#"<script>alert("xss")</script>"#;
+const ESCAPED_PAYLOAD: &str = "&lt;script&gt;alert(&quot;xss&quot;)&lt;/script&gt;";
+
+fn assert_payload_is_escaped(html_page: &str) {
+    assert!(!html_page.contains(PAYLOAD));
+    assert!(html_page.contains(ESCAPED_PAYLOAD));
+}
+
+#[tokio::test]
+async fn flash_messages_are_escaped_on_the_login_page() {
+    let app = helpers::spawn_app().await;
+
+    app.add_flash_message(PAYLOAD);
+    let html_page = app.get_login_html().await;
+
+    assert_payload_is_escaped(&html_page);
+}
+
+#[tokio::test]
+async fn flash_messages_are_escaped_on_admin_pages() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_change_password_html().await);
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_publish_newsletter_html().await);
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_admin_sessions_html().await);
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_admin_tokens_html().await);
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_admin_subscribers_html("").await);
+
+    app.add_flash_message(PAYLOAD);
+    assert_payload_is_escaped(&app.get_admin_lists_html().await);
+}
+
+#[tokio::test]
+async fn the_username_is_escaped_on_the_dashboard() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+    sqlx::query!(
+        "UPDATE users SET username = $1 WHERE user_id = $2",
+        PAYLOAD,
+        app.test_user.user_id
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let html_page = app.get_admin_dashboard_html().await;
+
+    assert_payload_is_escaped(&html_page);
+}
+
+#[tokio::test]
+async fn the_newsletter_name_is_escaped_on_the_newsletters_page() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let response = app
+        .post_create_newsletter(&serde_json::json!({
+            "name": PAYLOAD,
+            "slug": "xss"
+        }))
+        .await;
+    helpers::assert_is_redirect_to(&response, "/admin/lists");
+
+    let html_page = app.get_admin_lists_html().await;
+
+    assert_payload_is_escaped(&html_page);
+}
+
+#[tokio::test]
+async fn the_user_agent_is_escaped_on_the_sessions_page() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login_with_user_agent(&app, PAYLOAD).await;
+
+    app.test_user.login(&app).await;
+    let html_page = app.get_admin_sessions_html().await;
+
+    assert_payload_is_escaped(&html_page);
+}
+
+#[tokio::test]
+async fn the_token_name_is_escaped_on_the_tokens_page() {
+    let app = helpers::spawn_app().await;
+    app.test_user.login(&app).await;
+
+    let body = serde_urlencoded::to_string([("name", PAYLOAD), ("scopes", "newsletters:publish")])
+        .unwrap();
+    let response = app.post_create_api_token(body).await;
+    assert_eq!(200, response.status());
+    assert_payload_is_escaped(&response.text().await.unwrap());
+
+    let html_page = app.get_admin_tokens_html().await;
+
+    assert_payload_is_escaped(&html_page);
+}